@@ -0,0 +1,85 @@
+use songbird::input::{ core::io::MediaSource, AudioStream, AudioStreamError, AuxMetadata, Compose };
+
+use crate::{ innertube::YouTubeAudio, library::LocalAudio };
+
+/// The audio of a queued track, wherever it comes from.
+#[derive(Debug, Clone)]
+pub(crate) enum TrackAudio {
+    YouTube(YouTubeAudio),
+    Local(LocalAudio),
+}
+
+impl TrackAudio {
+    /// Identifies the track: a YouTube video id, or a `local:` id from the library.
+    pub(crate) fn id(&self) -> &str {
+        match self {
+            Self::YouTube(audio) => audio.id(),
+            Self::Local(audio) => audio.id(),
+        }
+    }
+
+    /// The YouTube video id, for what only YouTube can do, like lyrics and radios.
+    pub(crate) fn youtube_id(&self) -> Option<&str> {
+        match self {
+            Self::YouTube(audio) => Some(audio.id()),
+            Self::Local(_) => None,
+        }
+    }
+
+    pub(crate) fn metadata(&self) -> &AuxMetadata {
+        match self {
+            Self::YouTube(audio) => audio.metadata(),
+            Self::Local(audio) => audio.metadata(),
+        }
+    }
+
+    /// How much louder than YouTube's reference level the track is, if known.
+    pub(crate) fn loudness_db(&self) -> Option<f32> {
+        match self {
+            Self::YouTube(audio) => audio.loudness_db(),
+            Self::Local(_) => None,
+        }
+    }
+}
+
+impl From<YouTubeAudio> for TrackAudio {
+    fn from(audio: YouTubeAudio) -> Self {
+        Self::YouTube(audio)
+    }
+}
+
+impl From<LocalAudio> for TrackAudio {
+    fn from(audio: LocalAudio) -> Self {
+        Self::Local(audio)
+    }
+}
+
+#[async_trait::async_trait]
+impl Compose for TrackAudio {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        match self {
+            Self::YouTube(audio) => audio.create(),
+            Self::Local(audio) => audio.create(),
+        }
+    }
+
+    async fn create_async(
+        &mut self
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        match self {
+            Self::YouTube(audio) => audio.create_async().await,
+            Self::Local(audio) => audio.create_async().await,
+        }
+    }
+
+    fn should_create_async(&self) -> bool {
+        match self {
+            Self::YouTube(audio) => audio.should_create_async(),
+            Self::Local(audio) => audio.should_create_async(),
+        }
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        Ok(self.metadata().clone())
+    }
+}
@@ -0,0 +1,82 @@
+//! Leaving voice channels when Berd is idle, or when nobody's listening.
+
+use std::sync::Arc;
+
+use twilight_model::id::{ marker::GuildMarker, Id };
+
+use crate::state::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum LeaveReason {
+    /// Nothing has been playing for a while.
+    Idle,
+    /// Every human has left the channel.
+    Alone,
+}
+
+/// Leaves the guild's voice channel after the timeout for `reason`, unless
+/// [`cancel`] is called first. Scheduling again restarts the timer.
+pub(crate) fn schedule(state: Arc<State>, guild: Id<GuildMarker>, reason: LeaveReason) {
+    let timeout = match reason {
+        LeaveReason::Idle => state.config.idle_timeout,
+        LeaveReason::Alone => state.config.alone_timeout,
+    };
+
+    let task = tokio::spawn({
+        let state = state.clone();
+        async move {
+            tokio::time::sleep(timeout).await;
+
+            // things might have changed while the timer was sleeping
+            let still_applies = match reason {
+                LeaveReason::Idle => !state.has_guild_data(&guild),
+                LeaveReason::Alone => state.listeners(guild).await.is_some_and(|l| l.is_empty()),
+            };
+            if !still_applies {
+                return;
+            }
+
+            // forget this timer without aborting it, as `leave` would
+            state.leave_timers.remove(&(guild, reason));
+
+            tracing::info!(?reason, "Leaving voice in guild {}", guild);
+            if let Err(e) = leave(&state, guild).await {
+                tracing::error!(?e, "Failed to leave voice");
+            }
+        }
+    });
+
+    if let Some(previous) = state.leave_timers.insert((guild, reason), task.abort_handle()) {
+        previous.abort();
+    }
+}
+
+pub(crate) fn cancel(state: &State, guild: Id<GuildMarker>, reason: LeaveReason) {
+    if let Some((_, timer)) = state.leave_timers.remove(&(guild, reason)) {
+        timer.abort();
+    }
+}
+
+/// Ends the session and disconnects from voice right away.
+pub(crate) async fn leave(state: &State, guild: Id<GuildMarker>) -> anyhow::Result<()> {
+    for reason in [LeaveReason::Idle, LeaveReason::Alone] {
+        cancel(state, guild, reason);
+    }
+
+    state.end_session(&guild);
+    if state.songbird.get(guild).is_some() {
+        state.songbird.remove(guild).await?;
+    }
+
+    Ok(())
+}
+
+/// Checks whether Berd was left alone in the guild's voice channel, after someone's voice
+/// state changed.
+pub(crate) async fn check_alone(state: Arc<State>, guild: Id<GuildMarker>) {
+    match state.listeners(guild).await {
+        Some(listeners) if listeners.is_empty() => schedule(state, guild, LeaveReason::Alone),
+        Some(_) => cancel(&state, guild, LeaveReason::Alone),
+        None => {}
+    }
+}
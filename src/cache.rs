@@ -1,5 +1,8 @@
 use dashmap::{ DashMap, mapref::one::Ref };
-use twilight_model::{ id::{ marker::UserMarker, Id }, voice::VoiceState };
+use twilight_model::{
+    id::{ marker::{ ChannelMarker, GuildMarker, UserMarker }, Id },
+    voice::VoiceState,
+};
 
 #[derive(Debug, Default)]
 pub(crate) struct Cache {
@@ -21,4 +24,20 @@ impl Cache {
     ) -> Option<Ref<'_, Id<UserMarker>, VoiceState>> {
         self.voice_states.get(id)
     }
+
+    /// Non-bot users connected to the given voice channel.
+    ///
+    /// Users whose member info is unknown are counted as humans.
+    pub(crate) fn listeners(
+        &self,
+        guild: Id<GuildMarker>,
+        channel: Id<ChannelMarker>
+    ) -> Vec<Id<UserMarker>> {
+        self.voice_states
+            .iter()
+            .filter(|vs| vs.guild_id == Some(guild) && vs.channel_id == Some(channel))
+            .filter(|vs| !vs.member.as_ref().is_some_and(|member| member.user.bot))
+            .map(|vs| vs.user_id)
+            .collect()
+    }
 }
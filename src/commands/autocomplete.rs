@@ -0,0 +1,134 @@
+use std::{ sync::Arc, time::Duration };
+
+use anyhow::Context;
+use twilight_interactions::command::{ AutocompleteValue, CommandModel };
+use twilight_model::application::{
+    command::{ CommandOptionChoice, CommandOptionChoiceValue },
+    interaction::Interaction,
+};
+
+use crate::{
+    innertube::InnerTube,
+    interaction_context::CommandInteractionContext,
+    links,
+    state::State,
+};
+
+/// How long to wait for the user to stop typing before asking YouTube Music.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Discord wants an answer within 3 seconds, so give up on YouTube Music before that.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Discord shows at most this many choices.
+const MAX_CHOICES: usize = 25;
+
+/// Discord's limit for choice names and values.
+const CHOICE_LIMIT: usize = 100;
+
+/// The subcommands with options that can be autocompleted.
+#[derive(CommandModel)]
+pub(crate) enum BerdAutocomplete {
+    #[command(name = "play")] Play(QueryAutocomplete),
+    #[command(name = "playnext")] PlayNext(QueryAutocomplete),
+    #[command(name = "search")] Search(QueryAutocomplete),
+    #[command(name = "local")] Local(QueryAutocomplete),
+}
+
+#[derive(CommandModel)]
+#[command(autocomplete = true)]
+pub(crate) struct QueryAutocomplete {
+    pub(crate) query: AutocompleteValue<String>,
+}
+
+/// Suggests search queries: the user's recent searches first, then YouTube Music's.
+pub(crate) async fn query(
+    interaction: Interaction,
+    query: AutocompleteValue<String>,
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    let AutocompleteValue::Focused(query) = query else {
+        return Ok(());
+    };
+    let user = interaction.author_id().context("Interaction has no author")?;
+    state.suggestions.begin(user, interaction.id);
+
+    let mut suggestions = state.suggestions.recent(user, &query);
+    if !query.trim().is_empty() && links::parse(&query).is_none() {
+        let terms = match state.suggestions.cached(&query) {
+            Some(terms) => terms,
+            None => {
+                tokio::time::sleep(DEBOUNCE).await;
+                if state.suggestions.is_stale(user, interaction.id) {
+                    // Discord only shows the answer to the latest request anyway
+                    return Ok(());
+                }
+
+                let tube = InnerTube::new();
+                match tokio::time::timeout(FETCH_TIMEOUT, tube.suggest(&query)).await {
+                    Ok(Ok(terms)) => {
+                        state.suggestions.cache(&query, terms.clone());
+                        terms
+                    }
+                    Ok(Err(e)) => {
+                        tracing::warn!(?e, "Failed to get search suggestions");
+                        vec![]
+                    }
+                    Err(_) => {
+                        tracing::warn!("Search suggestions timed out");
+                        vec![]
+                    }
+                }
+            }
+        };
+
+        for term in terms {
+            if !suggestions.iter().any(|s| s.eq_ignore_ascii_case(&term)) {
+                suggestions.push(term);
+            }
+        }
+    }
+
+    let choices = suggestions
+        .into_iter()
+        .filter(|s| s.chars().count() <= CHOICE_LIMIT)
+        .take(MAX_CHOICES)
+        .map(|s| CommandOptionChoice {
+            name: s.clone(),
+            name_localizations: None,
+            value: CommandOptionChoiceValue::String(s),
+        })
+        .collect();
+
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+    ic.autocomplete(choices).await?;
+
+    Ok(())
+}
+
+/// Suggests songs from the local library. The values are library ids, which `/berd local` looks
+/// up directly.
+pub(crate) async fn local(
+    interaction: Interaction,
+    query: AutocompleteValue<String>,
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    let AutocompleteValue::Focused(query) = query else {
+        return Ok(());
+    };
+
+    let choices = state.library
+        .search(&query, MAX_CHOICES)
+        .into_iter()
+        .map(|audio| CommandOptionChoice {
+            name: audio.describe().chars().take(CHOICE_LIMIT).collect(),
+            name_localizations: None,
+            value: CommandOptionChoiceValue::String(audio.id().to_string()),
+        })
+        .collect();
+
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+    ic.autocomplete(choices).await?;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "autoplay", desc = "Keep playing similar songs when the queue runs out.")]
+pub(crate) struct AutoplayCommand {
+    /// Whether autoplay is on.
+    enabled: bool,
+}
+
+#[async_trait]
+impl HandleCommand for AutoplayCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        let enabled = self.enabled;
+        state.settings.update(guild, |settings| {
+            settings.autoplay = enabled;
+        }).await?;
+
+        if enabled {
+            ic.respond("Autoplay is on! I'll keep the music going when the queue runs out.").await?;
+        } else {
+            ic.respond("Autoplay is off.").await?;
+        }
+
+        Ok(())
+    }
+}
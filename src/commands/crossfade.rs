@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "crossfade", desc = "Fade songs into each other.")]
+pub(crate) struct CrossfadeCommand {
+    /// How long the fade lasts, in seconds. 0 turns it off.
+    #[command(min_value = 0, max_value = 12)]
+    seconds: i64,
+}
+
+#[async_trait]
+impl HandleCommand for CrossfadeCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let seconds = self.seconds.clamp(0, 12) as u8;
+
+        state.settings.update(guild, |settings| {
+            settings.crossfade = seconds;
+        }).await?;
+
+        if seconds == 0 {
+            ic.respond("Crossfade is off.").await?;
+        } else {
+            ic.respond(format!("Songs will now fade into each other over {seconds}s!")).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CommandOption, CreateCommand, CreateOption };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    dsp::{ EQ_BANDS, EQ_FREQUENCIES, EQ_MAX_GAIN },
+    interaction_context::CommandInteractionContext,
+    player,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "eq", desc = "Adjust the equalizer.")]
+pub(crate) enum EqCommand {
+    #[command(name = "set")] Set(EqSetCommand),
+    #[command(name = "preset")] Preset(EqPresetCommand),
+}
+
+#[async_trait]
+impl HandleCommand for EqCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Set(set) => set.handle_mut(interaction, state).await,
+            Self::Preset(preset) => preset.handle_mut(interaction, state).await,
+        }
+    }
+}
+
+#[derive(CreateOption, CommandOption)]
+pub(crate) enum BandOption {
+    #[option(name = "31 Hz", value = 0)]
+    Hz31,
+    #[option(name = "62 Hz", value = 1)]
+    Hz62,
+    #[option(name = "125 Hz", value = 2)]
+    Hz125,
+    #[option(name = "250 Hz", value = 3)]
+    Hz250,
+    #[option(name = "500 Hz", value = 4)]
+    Hz500,
+    #[option(name = "1 kHz", value = 5)]
+    Khz1,
+    #[option(name = "2 kHz", value = 6)]
+    Khz2,
+    #[option(name = "4 kHz", value = 7)]
+    Khz4,
+    #[option(name = "8 kHz", value = 8)]
+    Khz8,
+    #[option(name = "16 kHz", value = 9)]
+    Khz16,
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "set", desc = "Turn one band up or down.")]
+pub(crate) struct EqSetCommand {
+    /// The band to adjust.
+    band: BandOption,
+    /// How much to turn it up or down, in dB.
+    #[command(min_value = -12, max_value = 12)]
+    gain: i64,
+}
+
+#[async_trait]
+impl HandleCommand for EqSetCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let band = self.band.value() as usize;
+        let gain = self.gain.clamp(-i64::from(EQ_MAX_GAIN), i64::from(EQ_MAX_GAIN)) as i8;
+
+        let message = format!("Set the {} band to {gain:+} dB!", band_name(EQ_FREQUENCIES[band]));
+        set_eq(interaction, state, message, |eq| {
+            eq[band] = gain;
+        }).await
+    }
+}
+
+#[derive(CreateOption, CommandOption)]
+pub(crate) enum PresetOption {
+    #[option(name = "Flat", value = "flat")]
+    Flat,
+    #[option(name = "Rock", value = "rock")]
+    Rock,
+    #[option(name = "Pop", value = "pop")]
+    Pop,
+    #[option(name = "Classical", value = "classical")]
+    Classical,
+    #[option(name = "Vocal", value = "vocal")]
+    Vocal,
+}
+
+impl PresetOption {
+    /// The gain of each band, in dB.
+    fn gains(&self) -> [i8; EQ_BANDS] {
+        match self {
+            Self::Flat => [0; EQ_BANDS],
+            Self::Rock => [5, 4, 3, 1, -1, -1, 1, 3, 4, 5],
+            Self::Pop => [-1, 1, 3, 4, 3, 0, -1, -1, 1, 2],
+            Self::Classical => [4, 3, 2, 1, -1, -1, 0, 2, 3, 4],
+            Self::Vocal => [-3, -3, -2, 0, 3, 5, 5, 3, 0, -2],
+        }
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "preset", desc = "Load an equalizer preset.")]
+pub(crate) struct EqPresetCommand {
+    /// The preset to load.
+    name: PresetOption,
+}
+
+#[async_trait]
+impl HandleCommand for EqPresetCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let gains = self.name.gains();
+
+        let message = format!("Loaded the **{}** preset!", self.name.value());
+        set_eq(interaction, state, message, |eq| {
+            *eq = gains;
+        }).await
+    }
+}
+
+/// Changes the guild's equalizer, saves it and applies it to what's playing.
+async fn set_eq(
+    interaction: Interaction,
+    state: Arc<State>,
+    message: String,
+    f: impl FnOnce(&mut [i8; EQ_BANDS])
+) -> anyhow::Result<()> {
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+    let Some(guild) = interaction.guild_id else {
+        ic.respond("Hmm, we're not in a server!").await?;
+        return Ok(());
+    };
+
+    let effects = state.effects(guild);
+    let (was_neutral, eq) = {
+        let Ok(mut effects) = effects.write() else {
+            anyhow::bail!("Filters lock poisoned");
+        };
+        let was_neutral = effects.is_neutral();
+        f(&mut effects.eq);
+        (was_neutral, effects.eq)
+    };
+    state.settings.update(guild, |settings| {
+        settings.eq = eq;
+    }).await?;
+
+    player::effects_changed(&state, guild, was_neutral).await?;
+
+    let bands = EQ_FREQUENCIES.iter()
+        .zip(eq)
+        .map(|(freq, gain)| format!("`{} {gain:+}`", band_name(*freq)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    ic.respond(format!("{message}\n{bands}")).await?;
+
+    Ok(())
+}
+
+fn band_name(freq: u32) -> String {
+    if freq >= 1000 { format!("{} kHz", freq / 1000) } else { format!("{freq} Hz") }
+}
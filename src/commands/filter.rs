@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CommandOption, CreateCommand, CreateOption };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    dsp::Effects,
+    interaction_context::CommandInteractionContext,
+    player,
+    state::State,
+};
+
+#[derive(CreateOption, CommandOption)]
+pub(crate) enum FilterOption {
+    #[option(name = "Bass boost", value = "bassboost")]
+    BassBoost,
+
+    #[option(name = "Nightcore", value = "nightcore")]
+    Nightcore,
+
+    #[option(name = "Vaporwave", value = "vaporwave")]
+    Vaporwave,
+
+    #[option(name = "Speed", value = "speed")]
+    Speed,
+
+    #[option(name = "Pitch", value = "pitch")]
+    Pitch,
+
+    #[option(name = "8D", value = "8d")]
+    EightD,
+
+    #[option(name = "Off", value = "off")]
+    Off,
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "filter", desc = "Toggle an audio filter.")]
+pub(crate) struct FilterCommand {
+    /// The filter to toggle.
+    filter: FilterOption,
+    /// For speed and pitch: how much, where 1 is normal. Leave it out to reset.
+    #[command(min_value = 0.5, max_value = 2.0)]
+    amount: Option<f64>,
+}
+
+#[async_trait]
+impl HandleCommand for FilterCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let amount = self.amount.map(|amount| amount.clamp(0.5, 2.0) as f32);
+
+        let effects = state.effects(guild);
+        let (was_neutral, message) = {
+            let Ok(mut effects) = effects.write() else {
+                anyhow::bail!("Filters lock poisoned");
+            };
+            let was_neutral = effects.is_neutral();
+            let message = toggle(&mut effects, &self.filter, amount);
+            (was_neutral, message)
+        };
+
+        player::effects_changed(&state, guild, was_neutral).await?;
+
+        ic.respond(message).await?;
+
+        Ok(())
+    }
+}
+
+/// Applies the filter, returning what to tell the user.
+fn toggle(effects: &mut Effects, filter: &FilterOption, amount: Option<f32>) -> String {
+    let (name, on) = match filter {
+        FilterOption::BassBoost => {
+            effects.bass_boost = !effects.bass_boost;
+            ("Bass boost", effects.bass_boost)
+        }
+        FilterOption::Nightcore => {
+            effects.nightcore = !effects.nightcore;
+            effects.vaporwave = false;
+            ("Nightcore", effects.nightcore)
+        }
+        FilterOption::Vaporwave => {
+            effects.vaporwave = !effects.vaporwave;
+            effects.nightcore = false;
+            ("Vaporwave", effects.vaporwave)
+        }
+        FilterOption::Speed => {
+            effects.speed = amount.unwrap_or(1.0);
+            return format!("Speed set to {:.2}x!", effects.speed);
+        }
+        FilterOption::Pitch => {
+            effects.pitch = amount.unwrap_or(1.0);
+            return format!("Pitch set to {:.2}x!", effects.pitch);
+        }
+        FilterOption::EightD => {
+            effects.eight_d = !effects.eight_d;
+            ("8D", effects.eight_d)
+        }
+        FilterOption::Off => {
+            // the equalizer has its own command
+            *effects = Effects { eq: effects.eq, ..Default::default() };
+            return "All filters are off.".to_string();
+        }
+    };
+
+    format!("{name} is {}!", if on { "on" } else { "off" })
+}
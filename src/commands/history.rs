@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+use twilight_util::builder::{ embed::EmbedBuilder, InteractionResponseDataBuilder };
+
+use crate::{
+    audio::TrackAudio,
+    commands::traits::HandleCommand,
+    innertube::InnerTube,
+    interaction_context::CommandInteractionContext,
+    player::play_track,
+    state::{ QueuedTrack, State },
+    util::format_duration,
+};
+
+/// How many tracks `/berd history` lists.
+const HISTORY_SHOWN: usize = 15;
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "previous", desc = "Go back to the previous song.")]
+pub(crate) struct PreviousCommand;
+
+#[async_trait]
+impl HandleCommand for PreviousCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let requester = interaction.author_id().context("Interaction has no author")?;
+
+        let Some(entry) = state.previous(&guild) else {
+            ic.respond("There's nothing to go back to!").await?;
+            return Ok(());
+        };
+
+        ic.defer(false).await?;
+
+        // YouTube videos can be taken down, so make sure it's still there
+        let audio = match entry.audio.youtube_id() {
+            Some(video_id) => InnerTube::new().get(video_id).await.map(TrackAudio::from),
+            None => Ok(entry.audio.clone()),
+        };
+        let Ok(audio) = audio else {
+            ic
+                .create_followup(&interaction.token)
+                .content("Oh, no... I can't play that song anymore.").await?;
+            return Ok(());
+        };
+        let track = QueuedTrack::new(audio, requester);
+        let content = format!(
+            "Going back to **{}** - **{}**!",
+            entry.audio.metadata().artist.as_deref().unwrap_or("Unknown"),
+            entry.audio.metadata().track.as_deref().unwrap_or("Unknown")
+        );
+
+        if state.play_previous(&guild, track.clone())? {
+            ic.create_followup(&interaction.token).content(&content).await?;
+        } else if let Some(birdx) = state.songbird.get(guild) {
+            let mut call = birdx.lock().await;
+            play_track(&mut call, guild, track, state.clone())?;
+            ic.create_followup(&interaction.token).content(&content).await?;
+        } else {
+            ic
+                .create_followup(&interaction.token)
+                .content("Hmm... you sure we're in the same room?").await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "history", desc = "Show the songs that were played recently.")]
+pub(crate) struct HistoryCommand;
+
+#[async_trait]
+impl HandleCommand for HistoryCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        let description = state.history
+            .get(&guild)
+            .map(|history| {
+                history
+                    .iter()
+                    .rev()
+                    .take(HISTORY_SHOWN)
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        let metadata = entry.audio.metadata();
+                        format!(
+                            "`{}.` **{}** - {} `{}` · <@{}>\n",
+                            i + 1,
+                            metadata.track.as_deref().unwrap_or("Unknown"),
+                            metadata.artist.as_deref().unwrap_or("Unknown"),
+                            metadata.duration.map_or("?:??".to_string(), format_duration),
+                            entry.requester
+                        )
+                    })
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+
+        if description.is_empty() {
+            ic.respond("Nothing has been played yet!").await?;
+            return Ok(());
+        }
+
+        let embed = EmbedBuilder::new()
+            .title("Recently played")
+            .description(description)
+            .build();
+        ic.respond(InteractionResponseDataBuilder::new().embeds([embed]).build()).await?;
+
+        Ok(())
+    }
+}
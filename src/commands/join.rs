@@ -6,6 +6,7 @@ use twilight_interactions::command::{ CreateCommand, CommandModel };
 use twilight_model::application::interaction::{ Interaction, InteractionChannel };
 
 use crate::{
+    auto_leave::{ self, LeaveReason },
     commands::traits::HandleCommand,
     interaction_context::CommandInteractionContext,
     state::State,
@@ -72,6 +73,10 @@ impl HandleCommand for JoinCommand {
             return Ok(());
         }
 
+        if !state.has_guild_data(&guild) {
+            auto_leave::schedule(state.clone(), guild, LeaveReason::Idle);
+        }
+
         ic
             .create_followup(&interaction.token)
             .content(&format!("Joined <#{}>!", channel_id)).await?;
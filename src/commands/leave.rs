@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    auto_leave,
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "leave", desc = "Stop the music and leave the voice channel.")]
+pub(crate) struct LeaveCommand;
+
+#[async_trait]
+impl HandleCommand for LeaveCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        if state.songbird.get(guild).is_none() {
+            ic.respond("I'm not in a voice channel!").await?;
+            return Ok(());
+        }
+
+        auto_leave::leave(&state, guild).await?;
+        ic.respond("Bye!").await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::{ play::{ enqueue, Placement }, traits::HandleCommand },
+    interaction_context::CommandInteractionContext,
+    state::{ QueuedTrack, State },
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "local", desc = "Play a song from the local music library.")]
+pub(crate) struct LocalCommand {
+    /// The song to look for, by title, artist, album or file name.
+    #[command(autocomplete = true)]
+    query: String,
+}
+
+#[async_trait]
+impl HandleCommand for LocalCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let requester = interaction.author_id().context("Interaction has no author")?;
+
+        if state.library.is_empty() {
+            ic.respond("There's no local music to play!").await?;
+            return Ok(());
+        }
+
+        // autocompleted choices are ids, anything else is searched for
+        let audio = state.library
+            .get(&self.query)
+            .or_else(|| state.library.search(&self.query, 1).into_iter().next())
+            .cloned();
+        let Some(audio) = audio else {
+            ic.respond("No local songs found :(").await?;
+            return Ok(());
+        };
+
+        let track = QueuedTrack::new(audio, requester);
+        let content = enqueue(&state, guild, track, Placement::Back).await?;
+        ic.respond(content.as_deref().unwrap_or("Hmm... you sure we're in the same room?")).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use std::{ sync::Arc, time::Duration };
+
+use async_trait::async_trait;
+use songbird::tracks::TrackHandle;
+use tokio::time::Instant;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::message::{ Component, Embed },
+    id::{ marker::{ ApplicationMarker, GuildMarker }, Id },
+};
+use twilight_util::builder::{
+    embed::{ EmbedBuilder, EmbedFooterBuilder },
+    InteractionResponseDataBuilder,
+};
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    lyrics::Lyrics,
+    state::State,
+    util::{ page_buttons, EDIT_TIMEOUT },
+};
+
+const PAGE_SIZE: usize = 20;
+
+/// How many lines live lyrics show before and after the current one.
+const LIVE_LINES_BEFORE: usize = 2;
+const LIVE_LINES_AFTER: usize = 5;
+
+/// How often live lyrics check where the song is.
+const LIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "lyrics", desc = "Show the lyrics of the current song.")]
+pub(crate) struct LyricsCommand {
+    /// Follow along with the song as it plays. The timing is usually estimated.
+    live: Option<bool>,
+}
+
+#[async_trait]
+impl HandleCommand for LyricsCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let Some((handle, audio)) = state.guild_data
+            .get(&guild)
+            .map(|rf| (rf.handle.clone(), rf.current.audio.clone())) else {
+            ic.respond("Nothing is playing right now.").await?;
+            return Ok(());
+        };
+
+        ic.defer(false).await?;
+
+        // only YouTube Music has lyrics
+        let lyrics = match audio.youtube_id() {
+            Some(video_id) => match state.lyrics.get(video_id).await {
+                Ok(lyrics) => lyrics.map(|lyrics| (video_id, lyrics)),
+                Err(e) => {
+                    tracing::warn!(?e, "Failed to fetch lyrics");
+                    None
+                }
+            },
+            None => None,
+        };
+        let Some((video_id, lyrics)) = lyrics else {
+            ic
+                .create_followup(&interaction.token)
+                .content("No lyrics found for this song :(").await?;
+            return Ok(());
+        };
+
+        if self.live.unwrap_or(false) {
+            tokio::spawn(
+                follow(
+                    state.clone(),
+                    guild,
+                    interaction.application_id,
+                    interaction.token,
+                    handle,
+                    lyrics,
+                    audio.metadata().duration
+                )
+            );
+            return Ok(());
+        }
+
+        let (embed, buttons) = render_page(video_id, &lyrics, 0);
+        ic
+            .update_response(&interaction.token)
+            .embeds(Some(&[embed]))
+            .components(Some(&[buttons])).await?;
+
+        Ok(())
+    }
+}
+
+/// Handles the prev/next buttons, whose custom ids look like
+/// `lyrics:<video id>:<prev|next>:<page>`.
+pub(crate) async fn handle_component(
+    interaction: Interaction,
+    args: &[&str],
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+    let [video_id, _, page] = args else {
+        return Ok(());
+    };
+    let page = page.parse().unwrap_or(0);
+
+    let lyrics = state.lyrics.get(video_id).await.unwrap_or_else(|e| {
+        tracing::warn!(?e, "Failed to fetch lyrics");
+        None
+    });
+    let Some(lyrics) = lyrics else {
+        ic.update(
+            InteractionResponseDataBuilder::new()
+                .content("No lyrics found for this song :(")
+                .embeds([])
+                .components([])
+                .build()
+        ).await?;
+        return Ok(());
+    };
+    let (embed, buttons) = render_page(video_id, &lyrics, page);
+    ic.update(
+        InteractionResponseDataBuilder::new().embeds([embed]).components([buttons]).build()
+    ).await?;
+
+    Ok(())
+}
+
+fn render_page(video_id: &str, lyrics: &Lyrics, page: usize) -> (Embed, Component) {
+    let pages = lyrics.lines.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let description = lyrics.lines
+        .iter()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = EmbedBuilder::new()
+        .title(&lyrics.title)
+        .description(description)
+        .footer(
+            EmbedFooterBuilder::new(format!("Page {}/{} · {}", page + 1, pages, lyrics.source))
+        )
+        .build();
+    let buttons = page_buttons(&format!("lyrics:{video_id}"), page, pages);
+
+    (embed, buttons)
+}
+
+/// Keeps editing the response to highlight the line that's probably being sung, until the track
+/// stops.
+async fn follow(
+    state: Arc<State>,
+    guild: Id<GuildMarker>,
+    application_id: Id<ApplicationMarker>,
+    token: String,
+    handle: TrackHandle,
+    lyrics: Arc<Lyrics>,
+    duration: Option<Duration>
+) {
+    let deadline = Instant::now() + EDIT_TIMEOUT;
+    let mut interval = tokio::time::interval(LIVE_INTERVAL);
+    let mut shown = None;
+
+    while Instant::now() < deadline && state.is_current_handle(&guild, &handle) {
+        interval.tick().await;
+
+        let Ok(info) = handle.get_info().await else {
+            break;
+        };
+        let line = lyrics.line_at(info.position, duration);
+        if shown == Some(line) {
+            continue;
+        }
+        shown = Some(line);
+
+        let result = state.http
+            .interaction(application_id)
+            .update_response(&token)
+            .embeds(Some(&[render_live(&lyrics, line)])).await;
+        if let Err(e) = result {
+            tracing::warn!(?e, "Failed to update live lyrics");
+            break;
+        }
+    }
+}
+
+fn render_live(lyrics: &Lyrics, line: Option<usize>) -> Embed {
+    let start = line.map_or(0, |line| line.saturating_sub(LIVE_LINES_BEFORE));
+    let end = line.map_or(LIVE_LINES_AFTER, |line| line + LIVE_LINES_AFTER + 1);
+
+    let description = lyrics.lines
+        .iter()
+        .enumerate()
+        .take(end)
+        .skip(start)
+        .map(|(i, text)| if Some(i) == line { format!("**▶ {text}**") } else { text.clone() })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // most lyrics aren't timed and get spread over the song, which is only a guess
+    let footer = if lyrics.times.is_some() {
+        lyrics.source.clone()
+    } else {
+        format!("Timing is estimated · {}", lyrics.source)
+    };
+
+    EmbedBuilder::new()
+        .title(&lyrics.title)
+        .description(description)
+        .footer(EmbedFooterBuilder::new(footer))
+        .build()
+}
@@ -2,26 +2,64 @@ use std::sync::Arc;
 
 use anyhow::{ Context, Result };
 use twilight_interactions::command::{ CommandModel, CreateCommand };
-use twilight_model::application::interaction::{ application_command::CommandData, Interaction };
+use twilight_model::application::interaction::{
+    application_command::CommandData,
+    message_component::MessageComponentInteractionData,
+    Interaction,
+};
 
 use crate::{
     commands::{
+        autocomplete::BerdAutocomplete,
+        autoplay::AutoplayCommand,
+        crossfade::CrossfadeCommand,
+        eq::EqCommand,
+        filter::FilterCommand,
+        history::{ HistoryCommand, PreviousCommand },
         join::JoinCommand,
+        leave::LeaveCommand,
+        local::LocalCommand,
+        lyrics::LyricsCommand,
+        normalize::NormalizeCommand,
+        now_playing::NowPlayingCommand,
         pause_resume::{ PauseCommand, ResumeCommand },
-        play::PlayCommand,
+        play::{ PlayCommand, PlayNextCommand },
+        queue::QueueCommand,
+        queue_edit::{ ClearCommand, DedupeCommand, MoveCommand, RemoveCommand, ShuffleCommand },
         repeat::RepeatCommand,
+        search::SearchCommand,
+        seek::SeekCommand,
+        skip::{ SkipCommand, SkipToCommand },
         stop::StopCommand,
         traits::HandleCommand,
+        volume::VolumeCommand,
     },
     state::State,
 };
 
 mod traits;
+mod autocomplete;
 mod join;
 mod play;
 mod stop;
 mod pause_resume;
 mod repeat;
+mod queue;
+mod queue_edit;
+mod seek;
+mod volume;
+mod now_playing;
+mod leave;
+mod history;
+mod autoplay;
+mod search;
+mod crossfade;
+mod filter;
+mod eq;
+mod normalize;
+mod lyrics;
+mod local;
+mod skip;
 
 #[derive(CreateCommand, CommandModel)]
 #[command(name = "berd", desc = "The Berd music bot.")]
@@ -32,6 +70,29 @@ pub(crate) enum BerdCommands {
     #[command(name = "pause")] Pause(PauseCommand),
     #[command(name = "resume")] Resume(ResumeCommand),
     #[command(name = "repeat")] Repeat(RepeatCommand),
+    #[command(name = "skip")] Skip(SkipCommand),
+    #[command(name = "queue")] Queue(QueueCommand),
+    #[command(name = "remove")] Remove(RemoveCommand),
+    #[command(name = "move")] Move(MoveCommand),
+    #[command(name = "clear")] Clear(ClearCommand),
+    #[command(name = "shuffle")] Shuffle(ShuffleCommand),
+    #[command(name = "dedupe")] Dedupe(DedupeCommand),
+    #[command(name = "playnext")] PlayNext(PlayNextCommand),
+    #[command(name = "skipto")] SkipTo(SkipToCommand),
+    #[command(name = "seek")] Seek(SeekCommand),
+    #[command(name = "volume")] Volume(VolumeCommand),
+    #[command(name = "nowplaying")] NowPlaying(NowPlayingCommand),
+    #[command(name = "leave")] Leave(LeaveCommand),
+    #[command(name = "previous")] Previous(PreviousCommand),
+    #[command(name = "history")] History(HistoryCommand),
+    #[command(name = "autoplay")] Autoplay(AutoplayCommand),
+    #[command(name = "search")] Search(SearchCommand),
+    #[command(name = "crossfade")] Crossfade(CrossfadeCommand),
+    #[command(name = "filter")] Filter(FilterCommand),
+    #[command(name = "eq")] Eq(EqCommand),
+    #[command(name = "normalize")] Normalize(NormalizeCommand),
+    #[command(name = "lyrics")] Lyrics(LyricsCommand),
+    #[command(name = "local")] Local(LocalCommand),
 }
 
 impl BerdCommands {
@@ -48,6 +109,70 @@ impl BerdCommands {
             Self::Pause(mut pause) => pause.handle_mut(interaction, state).await,
             Self::Resume(mut resume) => resume.handle_mut(interaction, state).await,
             Self::Repeat(mut repeat) => repeat.handle_mut(interaction, state).await,
+            Self::Skip(mut skip) => skip.handle_mut(interaction, state).await,
+            Self::Queue(mut queue) => queue.handle_mut(interaction, state).await,
+            Self::Remove(mut remove) => remove.handle_mut(interaction, state).await,
+            Self::Move(mut move_) => move_.handle_mut(interaction, state).await,
+            Self::Clear(mut clear) => clear.handle_mut(interaction, state).await,
+            Self::Shuffle(mut shuffle) => shuffle.handle_mut(interaction, state).await,
+            Self::Dedupe(mut dedupe) => dedupe.handle_mut(interaction, state).await,
+            Self::PlayNext(mut play_next) => play_next.handle_mut(interaction, state).await,
+            Self::SkipTo(mut skip_to) => skip_to.handle_mut(interaction, state).await,
+            Self::Seek(mut seek) => seek.handle_mut(interaction, state).await,
+            Self::Volume(mut volume) => volume.handle_mut(interaction, state).await,
+            Self::NowPlaying(mut now_playing) => now_playing.handle_mut(interaction, state).await,
+            Self::Leave(mut leave) => leave.handle_mut(interaction, state).await,
+            Self::Previous(mut previous) => previous.handle_mut(interaction, state).await,
+            Self::History(mut history) => history.handle_mut(interaction, state).await,
+            Self::Autoplay(mut autoplay) => autoplay.handle_mut(interaction, state).await,
+            Self::Search(mut search) => search.handle_mut(interaction, state).await,
+            Self::Crossfade(mut crossfade) => crossfade.handle_mut(interaction, state).await,
+            Self::Filter(mut filter) => filter.handle_mut(interaction, state).await,
+            Self::Eq(mut eq) => eq.handle_mut(interaction, state).await,
+            Self::Normalize(mut normalize) => normalize.handle_mut(interaction, state).await,
+            Self::Lyrics(mut lyrics) => lyrics.handle_mut(interaction, state).await,
+            Self::Local(mut local) => local.handle_mut(interaction, state).await,
+        }
+    }
+
+    /// Suggests values for the option the user is typing in.
+    pub(crate) async fn run_autocomplete(
+        interaction: Interaction,
+        data: CommandData,
+        state: Arc<State>
+    ) -> Result<()> {
+        let command = BerdAutocomplete::from_interaction(data.into()).context(
+            "Parsing autocomplete data"
+        )?;
+        match command {
+            | BerdAutocomplete::Play(options)
+            | BerdAutocomplete::PlayNext(options)
+            | BerdAutocomplete::Search(options) => {
+                autocomplete::query(interaction, options.query, state).await
+            }
+            BerdAutocomplete::Local(options) => {
+                autocomplete::local(interaction, options.query, state).await
+            }
+        }
+    }
+
+    /// Routes message components by the prefix of their custom id, like `queue:next:2`.
+    pub(crate) async fn run_component(
+        interaction: Interaction,
+        data: MessageComponentInteractionData,
+        state: Arc<State>
+    ) -> Result<()> {
+        let args = data.custom_id.split(':').collect::<Vec<_>>();
+        match args.as_slice() {
+            ["queue", args @ ..] => queue::handle_component(interaction, args, state).await,
+            ["lyrics", args @ ..] => lyrics::handle_component(interaction, args, state).await,
+            ["search", args @ ..] => {
+                search::handle_component(interaction, args, &data.values, state).await
+            }
+            _ => {
+                tracing::warn!(custom_id = data.custom_id, "Unknown component");
+                Ok(())
+            }
         }
     }
 }
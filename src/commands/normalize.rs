@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "normalize", desc = "Play every song at the same loudness.")]
+pub(crate) struct NormalizeCommand {
+    /// Whether normalization is on.
+    enabled: bool,
+}
+
+#[async_trait]
+impl HandleCommand for NormalizeCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        let enabled = self.enabled;
+        state.settings.update(guild, |settings| {
+            settings.normalize = enabled;
+        }).await?;
+
+        // apply it to the current track too
+        if let Some((handle, volume)) = state.current_volume(&guild) {
+            handle.set_volume(volume)?;
+        }
+
+        if enabled {
+            ic.respond("Normalization is on! Songs will play at the same loudness.").await?;
+        } else {
+            ic.respond("Normalization is off.").await?;
+        }
+
+        Ok(())
+    }
+}
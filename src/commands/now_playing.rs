@@ -0,0 +1,150 @@
+use std::{ sync::Arc, time::Duration };
+
+use async_trait::async_trait;
+use songbird::tracks::{ PlayMode, TrackHandle };
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::message::Embed,
+    id::{ marker::{ ApplicationMarker, GuildMarker }, Id },
+};
+use twilight_util::builder::{
+    embed::{ EmbedBuilder, ImageSource },
+    InteractionResponseDataBuilder,
+};
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::{ QueuedTrack, State },
+    util::{ format_duration, EDIT_TIMEOUT },
+};
+
+/// How often the message is edited.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+const BAR_LENGTH: usize = 20;
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "nowplaying", desc = "Show the song that's playing.")]
+pub(crate) struct NowPlayingCommand;
+
+#[async_trait]
+impl HandleCommand for NowPlayingCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        let Some((handle, track)) = state.guild_data
+            .get(&guild)
+            .map(|rf| (rf.handle.clone(), rf.current.clone())) else {
+            ic.respond("Nothing is playing right now.").await?;
+            return Ok(());
+        };
+
+        let Some(embed) = render(&state, guild, &handle, &track).await else {
+            ic.respond("Nothing is playing right now.").await?;
+            return Ok(());
+        };
+        ic.respond(InteractionResponseDataBuilder::new().embeds([embed]).build()).await?;
+
+        let (application_id, token) = (interaction.application_id, interaction.token);
+        tokio::spawn(keep_updated(state.clone(), guild, application_id, token, handle, track));
+
+        Ok(())
+    }
+}
+
+/// Edits the response every few seconds, until the track ends or the token expires.
+async fn keep_updated(
+    state: Arc<State>,
+    guild: Id<GuildMarker>,
+    application_id: Id<ApplicationMarker>,
+    token: String,
+    handle: TrackHandle,
+    track: QueuedTrack
+) {
+    let client = state.http.interaction(application_id);
+    let started = tokio::time::Instant::now();
+
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    interval.tick().await;
+    while started.elapsed() < EDIT_TIMEOUT {
+        interval.tick().await;
+
+        let Some(embed) = render(&state, guild, &handle, &track).await else {
+            break;
+        };
+        if let Err(e) = client.update_response(&token).embeds(Some(&[embed])).await {
+            tracing::warn!(?e, "Failed to update the now playing message");
+            break;
+        }
+    }
+}
+
+/// Renders the now playing embed, or `None` if the track is no longer playing.
+async fn render(
+    state: &State,
+    guild: Id<GuildMarker>,
+    handle: &TrackHandle,
+    track: &QueuedTrack
+) -> Option<Embed> {
+    let info = handle.get_info().await.ok()?;
+    if info.playing.is_done() {
+        return None;
+    }
+
+    let metadata = track.audio.metadata();
+    // positions are in playback time, which filters can speed up or slow down
+    let duration = metadata.duration.map(|duration| state.playback_duration(guild, duration));
+    let timeline = match duration {
+        Some(duration) =>
+            format!(
+                "`{}` {} `{}`",
+                format_duration(info.position),
+                progress_bar(info.position, duration),
+                format_duration(duration)
+            ),
+        None => format!("`{}`", format_duration(info.position)),
+    };
+    let paused = if info.playing == PlayMode::Pause { " (paused)" } else { "" };
+
+    let mut embed = EmbedBuilder::new()
+        .title(metadata.track.as_deref().unwrap_or("Unknown"))
+        .description(
+            format!(
+                "by **{}**{}\n\n{}\n\nRequested by <@{}>",
+                metadata.artist.as_deref().unwrap_or("Unknown"),
+                paused,
+                timeline,
+                track.requester
+            )
+        );
+    if let Some(url) = &metadata.source_url {
+        embed = embed.url(url);
+    }
+    if let Some(url) = &metadata.thumbnail && let Ok(thumbnail) = ImageSource::url(url) {
+        embed = embed.thumbnail(thumbnail);
+    }
+
+    Some(embed.build())
+}
+
+fn progress_bar(position: Duration, duration: Duration) -> String {
+    let ratio = if duration.is_zero() {
+        0.0
+    } else {
+        (position.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0)
+    };
+    let knob = ((ratio * (BAR_LENGTH as f64)) as usize).min(BAR_LENGTH - 1);
+
+    (0..BAR_LENGTH).map(|i| if i == knob { "🔘" } else { "▬" }).collect()
+}
@@ -1,29 +1,27 @@
-use std::sync::Arc;
+use std::{ collections::VecDeque, sync::Arc };
 
 use anyhow::Context;
 use async_trait::async_trait;
-use songbird::{
-    input::Compose,
-    tracks::TrackHandle,
-    Event as SongbirdEvent,
-    EventContext,
-    EventHandler as SongbirdEventHandler,
-    TrackEvent,
-};
 use twilight_interactions::command::{ CommandModel, CreateCommand };
-use twilight_model::{ application::interaction::Interaction, id::{ marker::GuildMarker, Id } };
+use twilight_model::{
+    application::interaction::Interaction,
+    id::{ marker::{ GuildMarker, UserMarker }, Id },
+};
 
 use crate::{
     commands::traits::HandleCommand,
-    innertube::InnerTube,
+    innertube::{ Collection, InnerTube, YouTubeAudio },
     interaction_context::CommandInteractionContext,
-    state::State,
+    links::{ self, Link },
+    player::play_track,
+    state::{ QueuedTrack, State },
 };
 
 #[derive(CreateCommand, CommandModel)]
 #[command(name = "play", desc = "Play some music with Berd!")]
 pub(crate) struct PlayCommand {
-    /// The music to look for.
+    /// The music to look for, or a YouTube link to a song, playlist or album.
+    #[command(autocomplete = true)]
     query: String,
 }
 
@@ -34,113 +32,220 @@ impl HandleCommand for PlayCommand {
         interaction: Interaction,
         state: Arc<State>
     ) -> anyhow::Result<()> {
-        let ic = CommandInteractionContext::new(&state.http, &interaction);
-        ic.defer(false).await?;
-
-        let tube = InnerTube::new();
-        let mut results = tube.search(&self.query).await?;
-        if results.is_empty() {
-            ic.create_followup(&interaction.token).content("No results found :(").await?;
-            return Ok(());
-        }
+        play_query(&interaction, state, &self.query, Placement::Back).await
+    }
+}
 
-        let mut result = results
-            .drain(..)
-            .next()
-            .unwrap();
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "playnext", desc = "Play some music right after the current song.")]
+pub(crate) struct PlayNextCommand {
+    /// The music to look for, or a YouTube link to a song, playlist or album.
+    #[command(autocomplete = true)]
+    query: String,
+}
 
-        let Some(guild) = interaction.guild_id else {
-            ic.respond("Hmm, we're not in a server!").await?;
-            return Ok(());
-        };
+#[async_trait]
+impl HandleCommand for PlayNextCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        play_query(&interaction, state, &self.query, Placement::Front).await
+    }
+}
 
-        if let Some(birdx) = state.songbird.get(guild) {
-            let mut call = birdx.lock().await;
-            if state.has_guild_data(&guild) {
-                // when there's guild data, there's 100% a handle.
-                // guranteed because there is no Option<T> block
+/// Where a new track goes, when something is already playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Placement {
+    Back,
+    Front,
+}
 
-                if let Ok(metadata) = result.aux_metadata().await {
-                    ic
-                        .create_followup(&interaction.token)
-                        .content(
-                            &format!(
-                                "Added **{}** - **{}** to queue!",
-                                metadata.artist.unwrap_or("Unknown".to_string()),
-                                metadata.track.unwrap_or("Unknown".to_string())
-                            )
-                        ).await?;
+/// Searches for `query`, then plays the first result, or queues it if something's playing.
+pub(crate) async fn play_query(
+    interaction: &Interaction,
+    state: Arc<State>,
+    query: &str,
+    placement: Placement
+) -> anyhow::Result<()> {
+    let ic = CommandInteractionContext::new(&state.http, interaction);
+
+    let Some(guild) = interaction.guild_id else {
+        ic.respond("Hmm, we're not in a server!").await?;
+        return Ok(());
+    };
+    let requester = interaction.author_id().context("Interaction has no author")?;
+
+    ic.defer(false).await?;
+
+    let tube = InnerTube::new();
+    let (result, start) = match links::parse(query) {
+        Some(Link::Video { id, start }) => {
+            match tube.get(&id).await {
+                Ok(audio) => (audio, start),
+                // what looks like a bare id might just be a search, like "daft_punk_1"
+                Err(_) if links::is_video_id(query.trim()) => {
+                    let Some(result) = search_first(&tube, &state, requester, query).await? else {
+                        ic
+                            .create_followup(&interaction.token)
+                            .content("No results found :(").await?;
+                        return Ok(());
+                    };
+                    (result, None)
                 }
-
-                state.add_track(&guild, result);
-            } else {
-                if let Ok(metadata) = result.aux_metadata().await {
+                Err(_) => {
                     ic
                         .create_followup(&interaction.token)
-                        .content(
-                            &format!(
-                                "Playing **{}** - **{}**",
-                                metadata.artist.unwrap_or("Unknown".to_string()),
-                                metadata.track.unwrap_or("Unknown".to_string())
-                            )
-                        ).await?;
+                        .content("I can't play that video :(").await?;
+                    return Ok(());
                 }
-
-                let handle = call.play_input(result.into());
-                add_track_handle_events(&handle, guild, state.clone())?;
-                state.put_handle(guild, handle);
             }
-        } else {
-            ic
-                .create_followup(&interaction.token)
-                .content("Hmm... you sure we're in the same room?").await?;
         }
+        Some(Link::Playlist { id }) => {
+            let collection = tube.playlist(&id, state.config.playlist_limit).await;
+            return play_collection(interaction, state, guild, requester, collection, placement)
+                .await;
+        }
+        Some(Link::Album { id }) => {
+            let collection = tube.album(&id, state.config.playlist_limit).await;
+            return play_collection(interaction, state, guild, requester, collection, placement)
+                .await;
+        }
+        None => {
+            let Some(result) = search_first(&tube, &state, requester, query).await? else {
+                ic.create_followup(&interaction.token).content("No results found :(").await?;
+                return Ok(());
+            };
+            (result, None)
+        }
+    };
 
-        Ok(())
-    }
-}
+    let track = QueuedTrack::new(result, requester).with_start(start);
+    let content = enqueue(&state, guild, track, placement).await?;
+    ic
+        .create_followup(&interaction.token)
+        .content(content.as_deref().unwrap_or("Hmm... you sure we're in the same room?")).await?;
 
-struct TrackHandleEvents {
-    state: Arc<State>,
-    guild: Id<GuildMarker>,
+    Ok(())
 }
 
-#[async_trait]
-impl SongbirdEventHandler for TrackHandleEvents {
-    async fn act(&self, _: &EventContext<'_>) -> Option<SongbirdEvent> {
-        tracing::info!("Song finished");
-
-        if let Some(track) = self.state.pop_track(&self.guild) {
-            let birdx = self.state.songbird.get_or_insert(self.guild);
-            tracing::info!("Next song: {:?}", track);
-
-            let mut call = birdx.lock().await;
-            let handle = call.play_input(track.into());
+/// Searches for `query`, returning the first result.
+async fn search_first(
+    tube: &InnerTube,
+    state: &State,
+    requester: Id<UserMarker>,
+    query: &str
+) -> anyhow::Result<Option<YouTubeAudio>> {
+    state.suggestions.record_search(requester, query);
+    Ok(tube.search(query).await?.into_iter().next())
+}
 
-            if let Err(e) = add_track_handle_events(&handle, self.guild, self.state.clone()) {
-                tracing::error!(?e, "Failed to register event handler for track");
-                return None;
+/// Plays `track` right away, or queues it if something's already playing. Returns what to tell
+/// the user, or `None` if Berd isn't in a voice channel.
+pub(crate) async fn enqueue(
+    state: &Arc<State>,
+    guild: Id<GuildMarker>,
+    track: QueuedTrack,
+    placement: Placement
+) -> anyhow::Result<Option<String>> {
+    let Some(birdx) = state.songbird.get(guild) else {
+        return Ok(None);
+    };
+    let mut call = birdx.lock().await;
+
+    let metadata = track.audio.metadata();
+    let name = format!(
+        "**{}** - **{}**",
+        metadata.artist.as_deref().unwrap_or("Unknown"),
+        metadata.track.as_deref().unwrap_or("Unknown")
+    );
+
+    let content = if state.has_guild_data(&guild) {
+        // when there's guild data, there's 100% a handle.
+        // guranteed because there is no Option<T> block
+
+        match placement {
+            Placement::Back => {
+                state.add_track(&guild, track);
+                format!("Added {name} to queue!")
+            }
+            Placement::Front => {
+                state.add_track_front(&guild, track);
+                format!("Playing {name} next!")
             }
-
-            self.state.put_handle(self.guild, handle);
-        } else {
-            tracing::info!("Removed guild data: {}", &self.guild);
-            self.state.remove_guild_data(&self.guild);
         }
+    } else {
+        play_track(&mut call, guild, track, state.clone())?;
+        format!("Playing {name}")
+    };
 
-        None
-    }
+    Ok(Some(content))
 }
 
-fn add_track_handle_events(
-    handle: &TrackHandle,
+/// Plays or queues every track of a playlist or album, like [`play_query`] does for one.
+async fn play_collection(
+    interaction: &Interaction,
+    state: Arc<State>,
     guild: Id<GuildMarker>,
-    state: Arc<State>
+    requester: Id<UserMarker>,
+    collection: anyhow::Result<Collection>,
+    placement: Placement
 ) -> anyhow::Result<()> {
-    let event_handler = TrackHandleEvents { state, guild };
-    Ok(
-        handle
-            .add_event(SongbirdEvent::Track(TrackEvent::End), event_handler)
-            .context("Failed to register event handler for track")?
-    )
+    let ic = CommandInteractionContext::new(&state.http, interaction);
+
+    let collection = match collection {
+        Ok(collection) if !collection.tracks.is_empty() => collection,
+        Ok(_) => {
+            ic
+                .create_followup(&interaction.token)
+                .content("There's nothing I can play in there :(").await?;
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::warn!(?e, "Failed to get a playlist");
+            ic
+                .create_followup(&interaction.token)
+                .content("I can't play that playlist :(").await?;
+            return Ok(());
+        }
+    };
+
+    let Some(birdx) = state.songbird.get(guild) else {
+        ic
+            .create_followup(&interaction.token)
+            .content("Hmm... you sure we're in the same room?").await?;
+        return Ok(());
+    };
+    let mut call = birdx.lock().await;
+
+    let count = collection.tracks.len();
+    let mut tracks = collection.tracks
+        .into_iter()
+        .map(|audio| QueuedTrack::new(audio, requester))
+        .collect::<VecDeque<_>>();
+
+    if !state.has_guild_data(&guild) {
+        let first = tracks.pop_front().unwrap();
+        play_track(&mut call, guild, first, state.clone())?;
+    }
+    match placement {
+        Placement::Back => {
+            for track in tracks {
+                state.add_track(&guild, track);
+            }
+        }
+        Placement::Front => {
+            for track in tracks.into_iter().rev() {
+                state.add_track_front(&guild, track);
+            }
+        }
+    }
+
+    let noun = if count == 1 { "track" } else { "tracks" };
+    ic
+        .create_followup(&interaction.token)
+        .content(&format!("Queued {count} {noun} from **{}**!", collection.name)).await?;
+
+    Ok(())
 }
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::{
+    application::interaction::Interaction,
+    http::interaction::InteractionResponseData,
+    id::{ marker::GuildMarker, Id },
+};
+use twilight_util::builder::{
+    embed::{ EmbedBuilder, EmbedFooterBuilder },
+    InteractionResponseDataBuilder,
+};
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::{ QueuedTrack, State },
+    util::{ format_duration, page_buttons },
+};
+
+const PAGE_SIZE: usize = 10;
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "queue", desc = "Show what's coming up next.")]
+pub(crate) struct QueueCommand {
+    /// The page to show, starting from 1.
+    #[command(min_value = 1)]
+    page: Option<i64>,
+}
+
+#[async_trait]
+impl HandleCommand for QueueCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        let page = self.page.map_or(0, |page| (page.max(1) as usize) - 1);
+        ic.respond(render(&state, guild, page)).await?;
+
+        Ok(())
+    }
+}
+
+/// Handles the prev/next buttons, whose custom ids look like `queue:<prev|next>:<page>`.
+pub(crate) async fn handle_component(
+    interaction: Interaction,
+    args: &[&str],
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+    let (Some(guild), [_, page]) = (interaction.guild_id, args) else {
+        return Ok(());
+    };
+    let page = page.parse().unwrap_or(0);
+    ic.update(render(&state, guild, page)).await?;
+
+    Ok(())
+}
+
+fn render(state: &State, guild: Id<GuildMarker>, page: usize) -> InteractionResponseData {
+    // snapshot the queue, so no ref is held while building the message
+    let Some((current, queue)) = state.guild_data
+        .get(&guild)
+        .map(|rf| (rf.current.clone(), rf.queue.iter().cloned().collect::<Vec<_>>())) else {
+        return InteractionResponseDataBuilder::new()
+            .content("Nothing is playing right now.")
+            .components([])
+            .embeds([])
+            .build();
+    };
+
+    let pages = queue.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut description = format!("**Now playing:** {}\n\n", describe(&current));
+    if queue.is_empty() {
+        description.push_str("The queue is empty.");
+    }
+    for (position, track) in queue.iter().enumerate().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        description.push_str(&format!("`{}.` {}\n", position + 1, describe(track)));
+    }
+
+    let embed = EmbedBuilder::new()
+        .title("Queue")
+        .description(description)
+        .footer(
+            EmbedFooterBuilder::new(
+                format!("Page {}/{} · {} tracks", page + 1, pages, queue.len())
+            )
+        )
+        .build();
+
+    InteractionResponseDataBuilder::new()
+        .content("")
+        .embeds([embed])
+        .components([page_buttons("queue", page, pages)])
+        .build()
+}
+
+fn describe(track: &QueuedTrack) -> String {
+    let metadata = track.audio.metadata();
+    format!(
+        "**{}** - {} `{}` · <@{}>",
+        metadata.track.as_deref().unwrap_or("Unknown"),
+        metadata.artist.as_deref().unwrap_or("Unknown"),
+        metadata.duration.map_or("?:??".to_string(), format_duration),
+        track.requester
+    )
+}
+
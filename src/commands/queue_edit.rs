@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::{ application::interaction::Interaction, id::{ marker::GuildMarker, Id } };
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::{ QueuedTrack, State },
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "remove", desc = "Remove a song from the queue.")]
+pub(crate) struct RemoveCommand {
+    /// The position of the song in the queue.
+    #[command(min_value = 1)]
+    position: i64,
+}
+
+#[async_trait]
+impl HandleCommand for RemoveCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let position = self.position;
+        handler(interaction, state, |state, guild| {
+            match state.remove_track(guild, index(position)) {
+                Some(track) => format!("Removed {} from the queue!", describe(&track)),
+                None => format!("There's no song at position {position}."),
+            }
+        }).await
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "move", desc = "Move a song to another position in the queue.")]
+pub(crate) struct MoveCommand {
+    /// The current position of the song.
+    #[command(min_value = 1)]
+    from: i64,
+
+    /// Where to move the song to.
+    #[command(min_value = 1)]
+    to: i64,
+}
+
+#[async_trait]
+impl HandleCommand for MoveCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let (from, to) = (self.from, self.to);
+        handler(interaction, state, |state, guild| {
+            match state.move_track(guild, index(from), index(to)) {
+                Some((track, to)) => format!("Moved {} to position {}!", describe(&track), to + 1),
+                None => format!("There's no song at position {from}."),
+            }
+        }).await
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "clear", desc = "Remove every song from the queue.")]
+pub(crate) struct ClearCommand;
+
+#[async_trait]
+impl HandleCommand for ClearCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        handler(interaction, state, |state, guild| {
+            let count = state.clear_queue(guild).unwrap_or(0);
+            format!("Cleared {count} songs from the queue!")
+        }).await
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "shuffle", desc = "Shuffle the queue.")]
+pub(crate) struct ShuffleCommand;
+
+#[async_trait]
+impl HandleCommand for ShuffleCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        handler(interaction, state, |state, guild| {
+            let count = state.shuffle_queue(guild).unwrap_or(0);
+            format!("Shuffled {count} songs!")
+        }).await
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "dedupe", desc = "Remove repeated songs from the queue.")]
+pub(crate) struct DedupeCommand;
+
+#[async_trait]
+impl HandleCommand for DedupeCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        handler(interaction, state, |state, guild| {
+            let count = state.dedupe_queue(guild).unwrap_or(0);
+            format!("Removed {count} repeated songs!")
+        }).await
+    }
+}
+
+/// Runs `edit` against the guild's queue and responds with the message it returns.
+///
+/// `edit` is not async, so that no ref to the guild data can be held across awaits.
+async fn handler(
+    interaction: Interaction,
+    state: Arc<State>,
+    edit: impl FnOnce(&State, &Id<GuildMarker>) -> String
+) -> anyhow::Result<()> {
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+    let Some(guild) = interaction.guild_id else {
+        ic.respond("Hmm, we're not in a server!").await?;
+        return Ok(());
+    };
+
+    if !state.has_guild_data(&guild) {
+        ic.respond("Nothing is playing right now.").await?;
+        return Ok(());
+    }
+    ic.respond(edit(&state, &guild)).await?;
+
+    Ok(())
+}
+
+/// Converts a 1-based queue position into an index.
+fn index(position: i64) -> usize {
+    (position.max(1) as usize) - 1
+}
+
+fn describe(track: &QueuedTrack) -> String {
+    format!("**{}**", track.audio.metadata().track.as_deref().unwrap_or("Unknown"))
+}
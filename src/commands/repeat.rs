@@ -17,6 +17,9 @@ pub(crate) enum ModeOption {
 
     #[option(name = "No repeat", value = 1)]
     No,
+
+    #[option(name = "Queue", value = 2)]
+    Queue,
 }
 
 impl ModeOption {
@@ -24,6 +27,7 @@ impl ModeOption {
         match self {
             Self::Single => RepeatMode::Single,
             Self::No => RepeatMode::No,
+            Self::Queue => RepeatMode::Queue,
         }
     }
 }
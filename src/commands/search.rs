@@ -0,0 +1,205 @@
+use std::{ sync::Arc, time::Duration };
+
+use anyhow::Context;
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::message::{
+        component::{ ActionRow, SelectMenu, SelectMenuOption, SelectMenuType },
+        Component,
+        MessageFlags,
+    },
+    id::{ marker::{ ApplicationMarker, InteractionMarker }, Id },
+};
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+use crate::{
+    commands::{ play::{ enqueue, Placement }, traits::HandleCommand },
+    innertube::{ InnerTube, YouTubeAudio },
+    interaction_context::CommandInteractionContext,
+    state::{ PendingSearch, QueuedTrack, State },
+    util::format_duration,
+};
+
+/// How many results the picker shows.
+const SEARCH_RESULTS: usize = 8;
+
+/// How long the picker can be used for.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Discord's limit for select menu labels and descriptions.
+const OPTION_TEXT_LIMIT: usize = 100;
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "search", desc = "Search for music, then pick what to play.")]
+pub(crate) struct SearchCommand {
+    /// The music to look for.
+    #[command(autocomplete = true)]
+    query: String,
+}
+
+#[async_trait]
+impl HandleCommand for SearchCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        if interaction.guild_id.is_none() {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        }
+        let user = interaction.author_id().context("Interaction has no author")?;
+
+        ic.defer(false).await?;
+
+        state.suggestions.record_search(user, &self.query);
+        let mut results = InnerTube::new().search(&self.query).await?;
+        results.truncate(SEARCH_RESULTS);
+        if results.is_empty() {
+            ic.create_followup(&interaction.token).content("No results found :(").await?;
+            return Ok(());
+        }
+
+        let menu = Component::ActionRow(ActionRow {
+            components: vec![
+                Component::SelectMenu(SelectMenu {
+                    channel_types: None,
+                    custom_id: format!("search:{}", interaction.id),
+                    default_values: None,
+                    disabled: false,
+                    kind: SelectMenuType::Text,
+                    max_values: Some(1),
+                    min_values: Some(1),
+                    options: Some(results.iter().enumerate().map(option).collect()),
+                    placeholder: Some("Pick a song to play".to_string()),
+                })
+            ],
+        });
+        state.searches.insert(interaction.id, PendingSearch { user, results });
+
+        ic
+            .update_response(&interaction.token)
+            .content(Some(&format!("Results for **{}**:", self.query)))
+            .components(Some(&[menu])).await?;
+
+        tokio::spawn(
+            expire(state.clone(), interaction.application_id, interaction.id, interaction.token)
+        );
+
+        Ok(())
+    }
+}
+
+/// Handles the picker, whose custom id looks like `search:<interaction id>`.
+pub(crate) async fn handle_component(
+    interaction: Interaction,
+    args: &[&str],
+    values: &[String],
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+    let (Some(guild), [search], [value]) = (interaction.guild_id, args, values) else {
+        return Ok(());
+    };
+    let user = interaction.author_id().context("Interaction has no author")?;
+    let search = search.parse::<Id<InteractionMarker>>()?;
+
+    let (owner, picked) = {
+        let Some(pending) = state.searches.get(&search) else {
+            ic.update(
+                InteractionResponseDataBuilder::new()
+                    .content("This search has expired.")
+                    .components([])
+                    .build()
+            ).await?;
+            return Ok(());
+        };
+        let picked = value.parse::<usize>().ok().and_then(|i| pending.results.get(i).cloned());
+        (pending.user, picked)
+    };
+    if owner != user {
+        ic.respond(
+            InteractionResponseDataBuilder::new()
+                .content("That's not your search! Use `/berd search` to find your own music.")
+                .flags(MessageFlags::EPHEMERAL)
+                .build()
+        ).await?;
+        return Ok(());
+    }
+    let Some(audio) = picked else {
+        return Ok(());
+    };
+    // whoever removes the search first gets to pick, in case of a double click
+    if state.searches.remove(&search).is_none() {
+        return Ok(());
+    }
+
+    let track = QueuedTrack::new(audio, user);
+    let content = enqueue(&state, guild, track, Placement::Back).await?;
+    ic.update(
+        InteractionResponseDataBuilder::new()
+            .content(content.as_deref().unwrap_or("Hmm... you sure we're in the same room?"))
+            .components([])
+            .build()
+    ).await?;
+
+    Ok(())
+}
+
+/// Takes the picker away once it times out, unless something was picked already.
+async fn expire(
+    state: Arc<State>,
+    application_id: Id<ApplicationMarker>,
+    search: Id<InteractionMarker>,
+    token: String
+) {
+    tokio::time::sleep(SEARCH_TIMEOUT).await;
+
+    if state.searches.remove(&search).is_none() {
+        return;
+    }
+    let result = state.http
+        .interaction(application_id)
+        .update_response(&token)
+        .content(Some("This search has expired."))
+        .components(Some(&[])).await;
+    if let Err(e) = result {
+        tracing::warn!(?e, "Failed to expire a search");
+    }
+}
+
+fn option((i, audio): (usize, &YouTubeAudio)) -> SelectMenuOption {
+    let metadata = audio.metadata();
+    SelectMenuOption {
+        default: false,
+        description: Some(
+            truncate(
+                format!(
+                    "{} · {}",
+                    metadata.artist.as_deref().unwrap_or("Unknown"),
+                    metadata.duration.map_or("?:??".to_string(), format_duration)
+                )
+            )
+        ),
+        emoji: None,
+        label: truncate(metadata.track.clone().unwrap_or_else(|| "Unknown".to_string())),
+        value: i.to_string(),
+    }
+}
+
+fn truncate(text: String) -> String {
+    if text.chars().count() <= OPTION_TEXT_LIMIT {
+        return text;
+    }
+    let mut text = text
+        .chars()
+        .take(OPTION_TEXT_LIMIT - 1)
+        .collect::<String>();
+    text.push('…');
+    text
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+    util::{ format_duration, parse_timestamp },
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "seek", desc = "Jump to a point in the song.")]
+pub(crate) struct SeekCommand {
+    /// Where to jump to, like `1:30`, or `+30s` / `-10s` to move from where we are.
+    position: String,
+}
+
+#[async_trait]
+impl HandleCommand for SeekCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+
+        let position = self.position.trim();
+        let (sign, timestamp) = match position.chars().next() {
+            Some(c @ ('+' | '-')) => (Some(c), &position[1..]),
+            _ => (None, position),
+        };
+        let Some(offset) = parse_timestamp(timestamp) else {
+            ic.respond("Hmm, I don't get that time. Try `1:30`, `+30s` or `-10s`.").await?;
+            return Ok(());
+        };
+
+        let Some((handle, duration)) = state.guild_data
+            .get(&guild)
+            .map(|rf| (rf.handle.clone(), rf.current.audio.metadata().duration)) else {
+            ic.respond("Nothing is playing right now.").await?;
+            return Ok(());
+        };
+
+        let target = match sign {
+            None => offset,
+            Some(sign) => {
+                let position = handle.get_info().await?.position;
+                if sign == '+' { position + offset } else { position.saturating_sub(offset) }
+            }
+        };
+        let duration = duration.map(|duration| state.playback_duration(guild, duration));
+        if duration.is_some_and(|duration| target >= duration) {
+            ic.respond("That's past the end of the song!").await?;
+            return Ok(());
+        }
+
+        ic.defer(false).await?;
+        let content = match handle.seek_async(target).await {
+            Ok(position) => format!("Jumped to `{}`!", format_duration(position)),
+            Err(e) => {
+                tracing::error!(?e, "Failed to seek");
+                "Oh, no... I couldn't jump there.".to_string()
+            }
+        };
+        ic.create_followup(&interaction.token).content(&content).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::{ application::interaction::Interaction, guild::Permissions };
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "skip", desc = "Skip the current song, or vote to skip it.")]
+pub(crate) struct SkipCommand;
+
+#[async_trait]
+impl HandleCommand for SkipCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let user_id = interaction.author_id().context("Interaction has no author")?;
+
+        let Some(listeners) = state.listeners(guild).await else {
+            ic.respond("Hmm... you sure we're in the same room?").await?;
+            return Ok(());
+        };
+        let is_dj = is_dj(&interaction, &state);
+
+        // votes are counted in one go, so that no ref is held across awaits
+        let votes = {
+            let Some(mut rf) = state.guild_data.get_mut(&guild) else {
+                ic.respond("There's nothing to skip!").await?;
+                return Ok(());
+            };
+
+            if
+                is_dj ||
+                rf.current.requester == user_id ||
+                listeners.len() <= state.config.vote_skip_listeners
+            {
+                Ok(None)
+            } else if !listeners.contains(&user_id) {
+                Err("You need to be listening to vote!")
+            } else {
+                rf.skip_votes.insert(user_id);
+                rf.skip_votes.retain(|voter| listeners.contains(voter));
+
+                let needed = (
+                    ((listeners.len() as f32) * state.config.vote_skip_ratio).ceil() as usize
+                ).max(1);
+                Ok(Some((rf.skip_votes.len(), needed)))
+            }
+        };
+
+        match votes {
+            Err(message) => ic.respond(message).await?,
+            Ok(Some((count, needed))) if count < needed => {
+                ic.respond(format!("Voted to skip! ({count}/{needed})")).await?;
+            }
+            Ok(_) => {
+                state.skip(&guild);
+                ic.respond("Skipped!").await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "skipto", desc = "Skip to a song in the queue, dropping the ones before it.")]
+pub(crate) struct SkipToCommand {
+    /// The position of the song in the queue.
+    #[command(min_value = 1)]
+    position: i64,
+}
+
+#[async_trait]
+impl HandleCommand for SkipToCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let Some(listeners) = state.listeners(guild).await else {
+            ic.respond("Hmm... you sure we're in the same room?").await?;
+            return Ok(());
+        };
+
+        // jumping the queue can't be voted on, so it's only up to DJs in busy channels
+        if listeners.len() > state.config.vote_skip_listeners && !is_dj(&interaction, &state) {
+            ic.respond("Only DJs can jump the queue while this many people are listening!").await?;
+            return Ok(());
+        }
+
+        let index = (self.position.max(1) as usize) - 1;
+        match state.skip_to(&guild, index)? {
+            Some(track) => {
+                let metadata = track.audio.metadata();
+                ic.respond(
+                    format!(
+                        "Skipped to **{}** - **{}**!",
+                        metadata.artist.as_deref().unwrap_or("Unknown"),
+                        metadata.track.as_deref().unwrap_or("Unknown")
+                    )
+                ).await?;
+            }
+            None => ic.respond(format!("There's no song at position {}.", self.position)).await?,
+        }
+
+        Ok(())
+    }
+}
+
+/// DJs have the configured DJ role, or can manage channels.
+fn is_dj(interaction: &Interaction, state: &State) -> bool {
+    interaction.member.as_ref().is_some_and(|member| {
+        state.config.dj_role.is_some_and(|role| member.roles.contains(&role)) ||
+            member.permissions.is_some_and(|p| p.contains(Permissions::MANAGE_CHANNELS))
+    })
+}
@@ -5,14 +5,18 @@ use twilight_interactions::command::{ CommandModel, CreateCommand };
 use twilight_model::application::interaction::Interaction;
 
 use crate::{
+    auto_leave::{ self, LeaveReason },
     commands::traits::HandleCommand,
     interaction_context::CommandInteractionContext,
     state::State,
 };
 
 #[derive(CreateCommand, CommandModel)]
-#[command(name = "stop", desc = "Stop the current music player")]
-pub(crate) struct StopCommand;
+#[command(name = "stop", desc = "Stop the music player and clear the queue")]
+pub(crate) struct StopCommand {
+    /// Also disconnect from the voice channel.
+    leave: Option<bool>,
+}
 
 #[async_trait]
 impl HandleCommand for StopCommand {
@@ -27,13 +31,19 @@ impl HandleCommand for StopCommand {
             ic.respond("Hmm, we're not in a server!").await?;
             return Ok(());
         };
-        if let Some(rf) = state.guild_data.get(&guild) {
-            rf.handle.stop()?;
+
+        let stopped = state.end_session(&guild);
+
+        let leave = self.leave.unwrap_or(false) && state.songbird.get(guild).is_some();
+        if leave {
+            auto_leave::leave(&state, guild).await?;
+            ic.respond("Stopped the music and left the channel. Bye!").await?;
+        } else if stopped {
+            auto_leave::schedule(state.clone(), guild, LeaveReason::Idle);
+            ic.respond("Stopped the music and cleared the queue!").await?;
         } else {
             ic.respond("Hmm... berd is not available for now. Are we in the same room?").await?;
-            return Ok(());
         }
-        ic.respond("Stopped this song!").await?;
 
         Ok(())
     }
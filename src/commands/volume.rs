@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use twilight_interactions::command::{ CommandModel, CreateCommand };
+use twilight_model::application::interaction::Interaction;
+
+use crate::{
+    commands::traits::HandleCommand,
+    interaction_context::CommandInteractionContext,
+    state::State,
+};
+
+#[derive(CreateCommand, CommandModel)]
+#[command(name = "volume", desc = "Set the volume.")]
+pub(crate) struct VolumeCommand {
+    /// The volume, in percent.
+    #[command(min_value = 0, max_value = 200)]
+    level: i64,
+
+    /// Also start future sessions at this volume.
+    default: Option<bool>,
+}
+
+#[async_trait]
+impl HandleCommand for VolumeCommand {
+    async fn handle_mut(
+        &mut self,
+        interaction: Interaction,
+        state: Arc<State>
+    ) -> anyhow::Result<()> {
+        let ic = CommandInteractionContext::new(&state.http, &interaction);
+
+        let Some(guild) = interaction.guild_id else {
+            ic.respond("Hmm, we're not in a server!").await?;
+            return Ok(());
+        };
+        let level = self.level.clamp(0, 200) as u8;
+
+        if let Some(mut rf) = state.guild_data.get_mut(&guild) {
+            rf.volume = level;
+        }
+        let playing = if let Some((handle, volume)) = state.current_volume(&guild) {
+            handle.set_volume(volume)?;
+            true
+        } else {
+            false
+        };
+
+        if self.default.unwrap_or(false) {
+            state.settings.update(guild, |settings| {
+                settings.volume = level;
+            }).await?;
+            ic.respond(
+                format!("Volume set to {level}%, and future sessions will start there!")
+            ).await?;
+        } else if playing {
+            ic.respond(format!("Volume set to {level}%!")).await?;
+        } else {
+            ic.respond(
+                "Nothing is playing right now. Use `default` to set the volume for next time!"
+            ).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use std::{ path::PathBuf, str::FromStr, time::Duration };
+
+use anyhow::{ Context, Result };
+use twilight_model::id::{ marker::RoleMarker, Id };
+
+/// Bot settings, read from the environment (or `.env`).
+#[derive(Debug)]
+pub(crate) struct Config {
+    /// Skips need votes once there are more than this many listeners.
+    pub(crate) vote_skip_listeners: usize,
+    /// The share of listeners that has to vote for a skip, between `0.0` and `1.0`.
+    pub(crate) vote_skip_ratio: f32,
+    /// Members with this role can always skip right away.
+    pub(crate) dj_role: Option<Id<RoleMarker>>,
+    /// Where per-guild settings are saved.
+    pub(crate) settings_path: PathBuf,
+    /// How long to stay in a voice channel with nothing playing.
+    pub(crate) idle_timeout: Duration,
+    /// How long to stay in a voice channel after every human has left.
+    pub(crate) alone_timeout: Duration,
+    /// The most tracks queued from a single playlist or album.
+    pub(crate) playlist_limit: usize,
+    /// A directory of audio files to play with `/berd local`.
+    pub(crate) music_dir: Option<PathBuf>,
+}
+
+impl Config {
+    pub(crate) fn from_env() -> Result<Self> {
+        Ok(Self {
+            vote_skip_listeners: var_or("BERD_VOTE_SKIP_LISTENERS", 2)?,
+            vote_skip_ratio: var_or("BERD_VOTE_SKIP_RATIO", 0.5f32)?.clamp(0.0, 1.0),
+            dj_role: var("BERD_DJ_ROLE_ID")?,
+            settings_path: var_or("BERD_SETTINGS_PATH", PathBuf::from("berd-settings.json"))?,
+            idle_timeout: Duration::from_secs(var_or("BERD_IDLE_TIMEOUT_SECS", 300)?),
+            alone_timeout: Duration::from_secs(var_or("BERD_ALONE_TIMEOUT_SECS", 30)?),
+            playlist_limit: var_or("BERD_PLAYLIST_LIMIT", 100)?,
+            music_dir: var::<PathBuf>("BERD_MUSIC_DIR")?.filter(|dir| !dir.as_os_str().is_empty()),
+        })
+    }
+}
+
+fn var<T: FromStr>(key: &str) -> Result<Option<T>>
+    where T::Err: std::error::Error + Send + Sync + 'static
+{
+    let Ok(value) = dotenvy::var(key) else {
+        return Ok(None);
+    };
+
+    Ok(Some(value.parse().with_context(|| format!("Invalid value for {key}"))?))
+}
+
+fn var_or<T: FromStr>(key: &str, default: T) -> Result<T>
+    where T::Err: std::error::Error + Send + Sync + 'static
+{
+    Ok(var(key)?.unwrap_or(default))
+}
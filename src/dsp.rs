@@ -0,0 +1,610 @@
+//! Audio filters, applied between the decoder and songbird.
+//!
+//! Tracks are only decoded here when a filter is on. Otherwise, songbird gets the stream as it
+//! is, which keeps Opus passthrough working.
+
+use std::{
+    f32::consts::PI,
+    io::{ Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom },
+    sync::{ Arc, RwLock },
+    time::Duration,
+};
+
+use songbird::input::{
+    codecs::{ get_codec_registry, get_probe },
+    AudioStream,
+    AudioStreamError,
+    AuxMetadata,
+    Compose,
+    Input,
+    RawAdapter,
+};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{ Decoder, DecoderOptions },
+    errors::Error as SymphoniaError,
+    formats::{ FormatOptions, FormatReader, SeekMode, SeekTo },
+    io::{ MediaSource, MediaSourceStream },
+    meta::MetadataOptions,
+    units::Time,
+};
+
+use crate::audio::TrackAudio;
+
+/// How much nightcore speeds tracks up, pitch included.
+const NIGHTCORE_RATE: f32 = 1.25;
+
+/// How much vaporwave slows tracks down, pitch included.
+const VAPORWAVE_RATE: f32 = 0.8;
+
+const BASS_BOOST_FREQ: f32 = 90.0;
+const BASS_BOOST_GAIN_DB: f32 = 9.0;
+
+/// How long the 8D effect takes to go around once, in seconds.
+const EIGHT_D_PERIOD: f32 = 8.0;
+
+/// The center frequencies of the equalizer's bands, in Hz.
+pub(crate) const EQ_FREQUENCIES: [u32; EQ_BANDS] = [
+    31, 62, 125, 250, 500, 1000, 2000, 4000, 8000, 16000,
+];
+
+pub(crate) const EQ_BANDS: usize = 10;
+
+/// How far each band can be turned up or down, in dB.
+pub(crate) const EQ_MAX_GAIN: i8 = 12;
+
+/// How wide each band is. About an octave, so neighbouring bands meet.
+const EQ_Q: f32 = std::f32::consts::SQRT_2;
+
+/// The length of the pieces tracks are cut into to change their speed, in frames.
+const GRAIN: usize = 2048;
+const HOP: usize = GRAIN / 2;
+
+/// The size of the header [`RawAdapter`] puts in front of the samples.
+const RAW_HEADER_LEN: u64 = 16;
+
+/// The output is always stereo.
+const CHANNELS: usize = 2;
+
+/// A guild's filters, shared with the tracks that are playing so changes apply right away.
+pub(crate) type SharedEffects = Arc<RwLock<Effects>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Effects {
+    pub(crate) bass_boost: bool,
+    pub(crate) nightcore: bool,
+    pub(crate) vaporwave: bool,
+    /// How fast tracks play, without changing their pitch. `1.0` is normal.
+    pub(crate) speed: f32,
+    /// How high tracks sound, without changing their speed. `1.0` is normal.
+    pub(crate) pitch: f32,
+    /// Slowly moves the sound around the listener.
+    pub(crate) eight_d: bool,
+    /// The gain of each equalizer band, in dB. See [`EQ_FREQUENCIES`].
+    pub(crate) eq: [i8; EQ_BANDS],
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            bass_boost: false,
+            nightcore: false,
+            vaporwave: false,
+            speed: 1.0,
+            pitch: 1.0,
+            eight_d: false,
+            eq: [0; EQ_BANDS],
+        }
+    }
+}
+
+impl Effects {
+    /// Whether the audio comes out unchanged.
+    pub(crate) fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// How much faster samples are played, which changes the pitch as well.
+    fn rate(&self) -> f32 {
+        let mut rate = self.pitch;
+        if self.nightcore {
+            rate *= NIGHTCORE_RATE;
+        }
+        if self.vaporwave {
+            rate *= VAPORWAVE_RATE;
+        }
+        rate
+    }
+
+    /// How much faster the track goes, on top of [`Effects::rate`], without changing the pitch.
+    fn tempo(&self) -> f32 {
+        self.speed / self.pitch
+    }
+
+    /// How much faster the track goes overall.
+    fn speed_factor(&self) -> f32 {
+        self.rate() * self.tempo()
+    }
+
+    /// How long `duration` of a track takes to play.
+    pub(crate) fn playback_duration(&self, duration: Duration) -> Duration {
+        duration.div_f32(self.speed_factor())
+    }
+}
+
+/// A track that goes through the guild's filters.
+pub(crate) struct FilteredAudio {
+    audio: TrackAudio,
+    effects: SharedEffects,
+}
+
+impl FilteredAudio {
+    pub(crate) fn new(audio: TrackAudio, effects: SharedEffects) -> Self {
+        Self { audio, effects }
+    }
+}
+
+#[async_trait::async_trait]
+impl Compose for FilteredAudio {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+
+    async fn create_async(
+        &mut self
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let stream = self.audio.create_async().await?;
+        // with no filters and a volume of exactly 1.0, songbird can send Opus streams to Discord
+        // as they are, without decoding and re-encoding them
+        if read_effects(&self.effects).is_neutral() {
+            return Ok(stream);
+        }
+
+        // probing reads from the stream, which blocks
+        let effects = self.effects.clone();
+        let source = tokio::task
+            ::spawn_blocking(move || FilteredSource::new(stream, effects)).await
+            .map_err(|e| AudioStreamError::Fail(e.into()))??;
+        let sample_rate = source.sample_rate;
+
+        Ok(AudioStream {
+            input: Box::new(RawAdapter::new(source, sample_rate, CHANNELS as u32)),
+            hint: None,
+        })
+    }
+
+    fn should_create_async(&self) -> bool {
+        true
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        self.audio.aux_metadata().await
+    }
+}
+
+impl From<FilteredAudio> for Input {
+    fn from(val: FilteredAudio) -> Self {
+        Input::Lazy(Box::new(val))
+    }
+}
+
+fn read_effects(effects: &SharedEffects) -> Effects {
+    effects
+        .read()
+        .map(|effects| effects.clone())
+        .unwrap_or_default()
+}
+
+/// Decodes a track and runs it through the filters, giving out raw stereo `f32` samples.
+struct FilteredSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    effects: SharedEffects,
+    chain: Chain,
+    decoded: Option<SampleBuffer<f32>>,
+    /// Filtered samples that haven't been read yet, as bytes.
+    pending: Vec<u8>,
+    pending_pos: usize,
+    done: bool,
+}
+
+impl FilteredSource {
+    fn new(
+        stream: AudioStream<Box<dyn MediaSource>>,
+        effects: SharedEffects
+    ) -> Result<Self, AudioStreamError> {
+        let probed = get_probe().format(
+            &stream.hint.unwrap_or_default(),
+            MediaSourceStream::new(stream.input, Default::default()),
+            &FormatOptions { enable_gapless: true, ..Default::default() },
+            &MetadataOptions::default()
+        ).map_err(|e| AudioStreamError::Fail(e.into()))?;
+        let format = probed.format;
+
+        let track = format.default_track().ok_or(AudioStreamError::Unsupported)?;
+        let decoder = get_codec_registry()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| AudioStreamError::Fail(e.into()))?;
+        let sample_rate = track.codec_params.sample_rate.ok_or(AudioStreamError::Unsupported)?;
+        let track_id = track.id;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            effects,
+            chain: Chain::new(sample_rate),
+            decoded: None,
+            pending: Vec::new(),
+            pending_pos: 0,
+            done: false,
+        })
+    }
+
+    /// Decodes and filters the next packet. Returns `false` at the end of the track.
+    fn fill(&mut self) -> IoResult<bool> {
+        let packet = match self.format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == IoErrorKind::UnexpectedEof => {
+                return Ok(false);
+            }
+            Err(SymphoniaError::ResetRequired) => {
+                self.decoder.reset();
+                return Ok(true);
+            }
+            Err(e) => {
+                return Err(IoError::other(e));
+            }
+        };
+        if packet.track_id() != self.track_id {
+            return Ok(true);
+        }
+
+        let decoded = match self.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a broken packet is skipped, not the whole track
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::debug!(e, "Skipping a packet that can't be decoded");
+                return Ok(true);
+            }
+            Err(e) => {
+                return Err(IoError::other(e));
+            }
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let samples = self.decoded.get_or_insert_with(|| {
+            SampleBuffer::new(decoded.capacity() as u64, spec)
+        });
+        if samples.capacity() < decoded.capacity() * channels {
+            *samples = SampleBuffer::new(decoded.capacity() as u64, spec);
+        }
+        samples.copy_interleaved_ref(decoded);
+
+        let stereo = samples
+            .samples()
+            .chunks(channels)
+            .flat_map(|frame| [frame[0], *frame.get(1).unwrap_or(&frame[0])])
+            .collect::<Vec<_>>();
+
+        let mut filtered = Vec::with_capacity(stereo.len());
+        self.chain.process(&stereo, &read_effects(&self.effects), &mut filtered);
+
+        self.pending.clear();
+        self.pending_pos = 0;
+        self.pending.extend(filtered.iter().flat_map(|sample| sample.to_le_bytes()));
+
+        Ok(true)
+    }
+}
+
+impl Read for FilteredSource {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        while self.pending_pos >= self.pending.len() {
+            if self.done {
+                return Ok(0);
+            }
+            if !self.fill()? {
+                self.done = true;
+            }
+        }
+
+        let n = buf.len().min(self.pending.len() - self.pending_pos);
+        buf[..n].copy_from_slice(&self.pending[self.pending_pos..self.pending_pos + n]);
+        self.pending_pos += n;
+
+        Ok(n)
+    }
+}
+
+impl Seek for FilteredSource {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        // `RawAdapter` passes on offsets with its header included
+        let SeekFrom::Start(pos) = pos else {
+            return Err(IoErrorKind::Unsupported.into());
+        };
+        let offset = pos.saturating_sub(RAW_HEADER_LEN);
+
+        // the filters might change the speed, so the time in the track is scaled by it
+        let frames = offset / ((CHANNELS * size_of::<f32>()) as u64);
+        let output_secs = (frames as f64) / f64::from(self.sample_rate);
+        let speed = f64::from(read_effects(&self.effects).speed_factor());
+
+        self.format
+            .seek(SeekMode::Coarse, SeekTo::Time {
+                time: Time::from(output_secs * speed),
+                track_id: Some(self.track_id),
+            })
+            .map_err(IoError::other)?;
+        self.decoder.reset();
+        self.chain.reset();
+        self.pending.clear();
+        self.pending_pos = 0;
+        self.done = false;
+
+        Ok(offset)
+    }
+}
+
+impl MediaSource for FilteredSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The filters, along with what they remember between packets.
+struct Chain {
+    sample_rate: f32,
+    bass_boost: Biquad,
+    bass_state: [BiquadState; CHANNELS],
+    eq: [Biquad; EQ_BANDS],
+    /// The gains `eq` was made for.
+    eq_gains: [i8; EQ_BANDS],
+    eq_state: [[BiquadState; CHANNELS]; EQ_BANDS],
+    /// Where the 8D effect is, in radians.
+    pan_phase: f32,
+    resampler: Resampler,
+    stretcher: Stretcher,
+}
+
+impl Chain {
+    fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f32;
+        Self {
+            sample_rate,
+            bass_boost: Biquad::low_shelf(sample_rate, BASS_BOOST_FREQ, BASS_BOOST_GAIN_DB),
+            bass_state: Default::default(),
+            eq: [Biquad::IDENTITY; EQ_BANDS],
+            eq_gains: [0; EQ_BANDS],
+            eq_state: Default::default(),
+            pan_phase: 0.0,
+            resampler: Resampler::default(),
+            stretcher: Stretcher::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.bass_state = Default::default();
+        self.eq_state = Default::default();
+        self.resampler = Resampler::default();
+        self.stretcher = Stretcher::new();
+    }
+
+    /// Filters interleaved stereo samples into `out`.
+    fn process(&mut self, samples: &[f32], effects: &Effects, out: &mut Vec<f32>) {
+        let mut samples = samples.to_vec();
+
+        if effects.bass_boost {
+            for frame in samples.chunks_exact_mut(CHANNELS) {
+                for (sample, state) in frame.iter_mut().zip(&mut self.bass_state) {
+                    *sample = self.bass_boost.process(state, *sample);
+                }
+            }
+        }
+
+        if effects.eq != self.eq_gains {
+            // the states are kept, so changing the gains doesn't click
+            for ((biquad, freq), gain) in self.eq.iter_mut().zip(EQ_FREQUENCIES).zip(effects.eq) {
+                // bands above what the sample rate can hold would make the filter blow up
+                *biquad = if (freq as f32) < self.sample_rate / 2.0 {
+                    Biquad::peaking(self.sample_rate, freq as f32, f32::from(gain))
+                } else {
+                    Biquad::IDENTITY
+                };
+            }
+            self.eq_gains = effects.eq;
+        }
+        for ((biquad, states), gain) in self.eq.iter().zip(&mut self.eq_state).zip(effects.eq) {
+            if gain == 0 {
+                continue;
+            }
+            for frame in samples.chunks_exact_mut(CHANNELS) {
+                for (sample, state) in frame.iter_mut().zip(states.iter_mut()) {
+                    *sample = biquad.process(state, *sample);
+                }
+            }
+        }
+
+        if effects.eight_d {
+            let step = (2.0 * PI) / (EIGHT_D_PERIOD * self.sample_rate);
+            for frame in samples.chunks_exact_mut(CHANNELS) {
+                let mid = (frame[0] + frame[1]) / 2.0;
+                // equal-power panning, from full left to full right and back
+                let angle = (self.pan_phase.sin() + 1.0) * (PI / 4.0);
+                frame[0] = mid * angle.cos();
+                frame[1] = mid * angle.sin();
+                self.pan_phase = (self.pan_phase + step) % (2.0 * PI);
+            }
+        }
+
+        let rate = effects.rate();
+        if rate != 1.0 {
+            let mut resampled = Vec::with_capacity(((samples.len() as f32) / rate) as usize + 2);
+            self.resampler.process(&samples, rate, &mut resampled);
+            samples = resampled;
+        }
+
+        let tempo = effects.tempo();
+        if tempo != 1.0 {
+            self.stretcher.process(&samples, tempo, out);
+        } else {
+            out.extend_from_slice(&samples);
+        }
+    }
+}
+
+/// A biquad filter, from the Audio EQ Cookbook.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    /// Lets everything through unchanged.
+    const IDENTITY: Self = Self { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 };
+
+    fn peaking(sample_rate: f32, freq: f32, gain_db: f32) -> Self {
+        let a = (10.0f32).powf(gain_db / 40.0);
+        let w0 = (2.0 * PI * freq) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * EQ_Q);
+
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos) / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+
+    fn low_shelf(sample_rate: f32, freq: f32, gain_db: f32) -> Self {
+        let a = (10.0f32).powf(gain_db / 40.0);
+        let w0 = (2.0 * PI * freq) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        // a shelf slope of 1
+        let alpha = (sin / 2.0) * (2.0f32).sqrt();
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let a0 = a + 1.0 + (a - 1.0) * cos + sqrt_a_alpha;
+        Self {
+            b0: (a * (a + 1.0 - (a - 1.0) * cos + sqrt_a_alpha)) / a0,
+            b1: (2.0 * a * (a - 1.0 - (a + 1.0) * cos)) / a0,
+            b2: (a * (a + 1.0 - (a - 1.0) * cos - sqrt_a_alpha)) / a0,
+            a1: (-2.0 * (a - 1.0 + (a + 1.0) * cos)) / a0,
+            a2: (a + 1.0 + (a - 1.0) * cos - sqrt_a_alpha) / a0,
+        }
+    }
+
+    fn process(&self, state: &mut BiquadState, x: f32) -> f32 {
+        let y =
+            self.b0 * x + self.b1 * state.x1 + self.b2 * state.x2 -
+            self.a1 * state.y1 -
+            self.a2 * state.y2;
+        *state = BiquadState { x1: x, x2: state.x1, y1: y, y2: state.y1 };
+        y
+    }
+}
+
+/// Plays samples faster or slower, which changes the pitch too.
+#[derive(Debug, Default)]
+struct Resampler {
+    /// The last frame of the previous packet, which the next one continues from.
+    previous: [f32; CHANNELS],
+    /// Where the next output frame is, in frames after `previous`.
+    pos: f64,
+}
+
+impl Resampler {
+    fn process(&mut self, samples: &[f32], rate: f32, out: &mut Vec<f32>) {
+        let frames = samples.len() / CHANNELS;
+        let frame = |i: usize| -> [f32; CHANNELS] {
+            if i == 0 {
+                self.previous
+            } else {
+                [samples[(i - 1) * CHANNELS], samples[(i - 1) * CHANNELS + 1]]
+            }
+        };
+
+        while (self.pos as usize) < frames {
+            let i = self.pos as usize;
+            let t = (self.pos - (i as f64)) as f32;
+            let (a, b) = (frame(i), frame(i + 1));
+            out.extend((0..CHANNELS).map(|c| a[c] + (b[c] - a[c]) * t));
+            self.pos += f64::from(rate);
+        }
+
+        if frames > 0 {
+            self.previous = frame(frames);
+            self.pos -= frames as f64;
+        }
+    }
+}
+
+/// Changes how fast samples go without changing their pitch, by overlapping pieces of them.
+#[derive(Debug)]
+struct Stretcher {
+    window: Vec<f32>,
+    input: Vec<f32>,
+    /// Where the next piece starts in `input`, in frames.
+    pos: f64,
+    /// Pieces being added together, `GRAIN` frames long.
+    overlap: Vec<f32>,
+}
+
+impl Stretcher {
+    fn new() -> Self {
+        Self {
+            // a Hann window, which adds up to 1 when overlapped by half
+            window: (0..GRAIN)
+                .map(|i| 0.5 - 0.5 * ((2.0 * PI * (i as f32)) / (GRAIN as f32)).cos())
+                .collect(),
+            input: Vec::new(),
+            pos: 0.0,
+            overlap: vec![0.0; GRAIN * CHANNELS],
+        }
+    }
+
+    fn process(&mut self, samples: &[f32], tempo: f32, out: &mut Vec<f32>) {
+        self.input.extend_from_slice(samples);
+
+        while ((self.pos as usize) + GRAIN) * CHANNELS <= self.input.len() {
+            let start = (self.pos as usize) * CHANNELS;
+            for (i, w) in self.window.iter().enumerate() {
+                for c in 0..CHANNELS {
+                    self.overlap[i * CHANNELS + c] += self.input[start + i * CHANNELS + c] * w;
+                }
+            }
+
+            out.extend_from_slice(&self.overlap[..HOP * CHANNELS]);
+            self.overlap.drain(..HOP * CHANNELS);
+            self.overlap.resize(GRAIN * CHANNELS, 0.0);
+            self.pos += (HOP as f64) * f64::from(tempo);
+        }
+
+        let consumed = (self.pos as usize).min(self.input.len() / CHANNELS);
+        self.input.drain(..consumed * CHANNELS);
+        self.pos -= consumed as f64;
+    }
+}
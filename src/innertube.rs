@@ -1,16 +1,23 @@
-use anyhow::Result;
-use reqwest::header::HeaderMap;
-use rustypipe::{ client::RustyPipe, model::MusicItem, param::StreamFilter };
+use std::{ collections::HashSet, sync::{ Arc, OnceLock }, time::Duration };
+
+use anyhow::{ Context, Result };
+use rustypipe::{
+    client::RustyPipe,
+    model::{ AudioCodec, AudioFormat, Lyrics, MusicItem, Thumbnail, TrackItem },
+    param::StreamFilter,
+};
 use songbird::input::{
-    core::io::MediaSource,
+    core::{ io::MediaSource, probe::Hint },
+    AsyncAdapterStream,
     AudioStream,
     AudioStreamError,
     AuxMetadata,
     Compose,
-    HttpRequest,
     Input,
 };
 
+use crate::stream::YouTubeStream;
+
 pub(crate) struct InnerTube {
     pub(crate) pipe: RustyPipe,
 }
@@ -27,50 +34,221 @@ impl InnerTube {
         &self,
         q: K
     ) -> Result<Vec<YouTubeAudio>> {
-        let mut items = self.pipe.query().music_search_main(q).await?.items;
-
-        let mut results = vec![];
-        for item in items.items.drain(..) {
-            if let MusicItem::Track(track) = item {
-                let player = self.pipe.query().player(track.id).await?;
-                let details = &player.details;
-
-                let stream = player.select_audio_stream(&StreamFilter::default()).unwrap();
-                let s = stream.url.clone();
-                let size = stream.size;
-                results.push(
-                    YouTubeAudio::new(s, size, AuxMetadata {
-                        track: details.name.clone(),
-                        artist: details.channel_name.clone(),
-                        album: None,
-                        date: None,
-                        channels: None,
-                        channel: None,
-                        start_time: None,
-                        duration: None,
-                        sample_rate: None,
-                        source_url: None,
-                        title: None,
-                        thumbnail: None,
-                    })
-                );
-            }
-        }
+        let items = self.pipe.query().music_search_main(q).await?.items;
+
+        Ok(
+            items.items
+                .into_iter()
+                .filter_map(|item| {
+                    match item {
+                        MusicItem::Track(track) => Some(YouTubeAudio::from(track)),
+                        _ => None,
+                    }
+                })
+                .collect()
+        )
+    }
+
+    /// Search terms YouTube Music suggests for a partial query.
+    pub(crate) async fn suggest(&self, q: &str) -> Result<Vec<String>> {
+        Ok(self.pipe.query().music_search_suggestion(q).await?.terms)
+    }
+
+    /// Up to `limit` tracks from the YouTube Music radio of a video, leaving out the ids in
+    /// `exclude`.
+    pub(crate) async fn radio(
+        &self,
+        video_id: &str,
+        exclude: &HashSet<String>,
+        limit: usize
+    ) -> Result<Vec<YouTubeAudio>> {
+        let radio = self.pipe.query().music_radio_track(video_id).await?;
+
+        Ok(
+            radio.items
+                .into_iter()
+                .filter(|track| track.id != video_id && !exclude.contains(&track.id))
+                .take(limit)
+                .map(YouTubeAudio::from)
+                .collect()
+        )
+    }
+
+    /// Up to `limit` tracks of a playlist.
+    pub(crate) async fn playlist(&self, playlist_id: &str, limit: usize) -> Result<Collection> {
+        let query = self.pipe.query();
+        let mut playlist = query.music_playlist(playlist_id).await?;
+        playlist.tracks.extend_limit(&query, limit).await?;
+
+        Ok(Collection {
+            name: playlist.name,
+            tracks: playlist.tracks.items
+                .into_iter()
+                .take(limit)
+                .map(YouTubeAudio::from)
+                .collect(),
+        })
+    }
+
+    /// Up to `limit` tracks of an album.
+    pub(crate) async fn album(&self, album_id: &str, limit: usize) -> Result<Collection> {
+        let album = self.pipe.query().music_album(album_id).await?;
+        let cover = widest(&album.cover);
+
+        Ok(Collection {
+            name: album.name,
+            tracks: album.tracks
+                .into_iter()
+                .take(limit)
+                .map(|track| {
+                    let mut audio = YouTubeAudio::from(track);
+                    // album tracks usually come without their own cover
+                    audio.metadata.thumbnail = audio.metadata.thumbnail.or_else(|| cover.clone());
+                    audio
+                })
+                .collect(),
+        })
+    }
+
+    /// Looks up a video, making sure it has something to play.
+    pub(crate) async fn get<K: AsRef<str> + std::fmt::Debug>(
+        &self,
+        video_id: K
+    ) -> Result<YouTubeAudio> {
+        let player = self.pipe.query().player(video_id).await?;
+        let details = &player.details;
+
+        player
+            .select_audio_stream(&StreamFilter::default())
+            .context("No audio stream available")?;
+
+        Ok(YouTubeAudio {
+            id: details.id.clone(),
+            metadata: AuxMetadata {
+                track: details.name.clone(),
+                artist: details.channel_name.clone(),
+                duration: Some(Duration::from_secs(details.duration.into())),
+                source_url: Some(source_url(&details.id)),
+                thumbnail: widest(&details.thumbnail),
+                ..AuxMetadata::default()
+            },
+            loudness_db: Arc::default(),
+        })
+    }
+
+    /// The lyrics of a track along with its title, if YouTube Music has them.
+    pub(crate) async fn lyrics(&self, video_id: &str) -> Result<Option<(String, Lyrics)>> {
+        let details = self.pipe.query().music_details(video_id).await?;
+        let Some(lyrics_id) = details.lyrics_id else {
+            return Ok(None);
+        };
+        let lyrics = self.pipe.query().music_lyrics(lyrics_id).await?;
+
+        Ok(Some((details.track.name, lyrics)))
+    }
+
+    /// Resolves the audio stream of a video, preferring Opus, which can be sent to Discord
+    /// without re-encoding. Stream URLs expire after a few hours, so this is done right before
+    /// playing.
+    pub(crate) async fn stream(&self, video_id: &str) -> Result<ResolvedStream> {
+        let player = self.pipe.query().player(video_id).await?;
+        let opus = StreamFilter::new()
+            .audio_formats([AudioFormat::Webm])
+            .audio_codecs([AudioCodec::Opus]);
+        let stream = player
+            .select_audio_stream(&opus)
+            .or_else(|| player.select_audio_stream(&StreamFilter::default()))
+            .context("No audio stream available")?;
 
-        Ok(results)
+        Ok(ResolvedStream {
+            url: stream.url.clone(),
+            size: stream.size,
+            // drop parameters like `; codecs="opus"`, which the probe doesn't know about
+            mime: stream.mime.split(';').next().unwrap_or_default().trim().to_string(),
+            extension: match stream.format {
+                AudioFormat::Webm => "webm",
+                _ => "m4a",
+            },
+            loudness_db: stream.loudness_db,
+        })
     }
 }
 
-#[derive(Debug)]
+fn source_url(video_id: &str) -> String {
+    format!("https://music.youtube.com/watch?v={video_id}")
+}
+
+fn widest(thumbnails: &[Thumbnail]) -> Option<String> {
+    thumbnails
+        .iter()
+        .max_by_key(|thumbnail| thumbnail.width)
+        .map(|thumbnail| thumbnail.url.clone())
+}
+
+/// The tracks of a playlist or album.
+pub(crate) struct Collection {
+    pub(crate) name: String,
+    pub(crate) tracks: Vec<YouTubeAudio>,
+}
+
+pub(crate) struct ResolvedStream {
+    pub(crate) url: String,
+    pub(crate) size: u64,
+    pub(crate) mime: String,
+    pub(crate) extension: &'static str,
+    /// How much louder than YouTube's reference level the track is.
+    pub(crate) loudness_db: Option<f32>,
+}
+
+/// A YouTube track. Its stream is only resolved once it starts playing.
+///
+/// This can be cloned and composed more than once, which is what allows a track to be
+/// repeated (see [`crate::state::RepeatMode`]).
+#[derive(Debug, Clone)]
 pub(crate) struct YouTubeAudio {
-    url: String,
-    size: u64,
-    metadata: Option<AuxMetadata>,
+    id: String,
+    metadata: AuxMetadata,
+    /// Known once the stream is resolved, and shared with the clones of this track.
+    loudness_db: Arc<OnceLock<f32>>,
 }
 
 impl YouTubeAudio {
-    pub(crate) fn new(url: String, size: u64, metadata: AuxMetadata) -> Self {
-        Self { url, size, metadata: Some(metadata) }
+    /// The YouTube video id.
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn metadata(&self) -> &AuxMetadata {
+        &self.metadata
+    }
+
+    /// How much louder than YouTube's reference level the track is, if known yet.
+    pub(crate) fn loudness_db(&self) -> Option<f32> {
+        self.loudness_db.get().copied()
+    }
+}
+
+impl From<TrackItem> for YouTubeAudio {
+    fn from(track: TrackItem) -> Self {
+        let artists = track.artists
+            .iter()
+            .map(|artist| artist.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Self {
+            metadata: AuxMetadata {
+                track: Some(track.name),
+                artist: Some(artists).filter(|artists| !artists.is_empty()),
+                album: track.album.map(|album| album.name),
+                duration: track.duration.map(|duration| Duration::from_secs(duration.into())),
+                source_url: Some(source_url(&track.id)),
+                thumbnail: widest(&track.cover),
+                ..AuxMetadata::default()
+            },
+            id: track.id,
+            loudness_db: Arc::default(),
+        }
     }
 }
 
@@ -88,15 +266,27 @@ impl Compose for YouTubeAudio {
     async fn create_async(
         &mut self
     ) -> std::result::Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
-        let client = reqwest::Client::new();
-        let mut req = HttpRequest {
-            client,
-            request: self.url.drain(..).collect::<String>(),
-            headers: HeaderMap::new(),
-            content_length: Some(self.size),
-        };
+        let resolved = InnerTube::new()
+            .stream(&self.id).await
+            .map_err(|e| AudioStreamError::Fail(e.into()))?;
+        if let Some(loudness_db) = resolved.loudness_db {
+            // a clone of this track might have set it already, to the same value
+            self.loudness_db.get_or_init(|| loudness_db);
+        }
+        let stream = YouTubeStream::new(
+            reqwest::Client::new(),
+            self.id.clone(),
+            resolved.url,
+            resolved.size
+        );
+
+        let mut hint = Hint::new();
+        hint.mime_type(&resolved.mime).with_extension(resolved.extension);
 
-        req.create_async().await
+        Ok(AudioStream {
+            input: Box::new(AsyncAdapterStream::new(Box::new(stream), 64 * 1024)),
+            hint: Some(hint),
+        })
     }
 
     fn should_create_async(&self) -> bool {
@@ -104,7 +294,7 @@ impl Compose for YouTubeAudio {
     }
 
     async fn aux_metadata(&mut self) -> std::result::Result<AuxMetadata, AudioStreamError> {
-        Ok(self.metadata.take().unwrap())
+        Ok(self.metadata.clone())
     }
 }
 
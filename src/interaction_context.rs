@@ -3,7 +3,7 @@ use std::ops::Deref;
 use anyhow::{ Context, Result };
 use twilight_http::{ Client as HttpClient, client::InteractionClient };
 use twilight_model::{
-    application::interaction::Interaction,
+    application::{ command::CommandOptionChoice, interaction::Interaction },
     channel::message::MessageFlags,
     http::interaction::{ InteractionResponse, InteractionResponseData, InteractionResponseType },
 };
@@ -25,6 +25,12 @@ impl ToInteractionResponseData for &str {
     }
 }
 
+impl ToInteractionResponseData for String {
+    fn into_ird(self) -> InteractionResponseData {
+        InteractionResponseDataBuilder::new().content(self).build()
+    }
+}
+
 pub(crate) struct CommandInteractionContext<'a> {
     pub(crate) client: InteractionClient<'a>,
     pub(crate) interaction: &'a Interaction,
@@ -72,6 +78,34 @@ impl<'a> CommandInteractionContext<'a> {
 
         Ok(())
     }
+
+    /// Answers an autocomplete interaction.
+    pub(crate) async fn autocomplete(&self, choices: Vec<CommandOptionChoice>) -> Result<()> {
+        self.client.create_response(
+            self.interaction.id,
+            &self.interaction.token,
+            &(InteractionResponse {
+                kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
+                data: Some(InteractionResponseDataBuilder::new().choices(choices).build()),
+            })
+        ).await?;
+
+        Ok(())
+    }
+
+    /// Edits the message a component is attached to.
+    pub(crate) async fn update<K: ToInteractionResponseData>(&self, data: K) -> Result<()> {
+        self.client.create_response(
+            self.interaction.id,
+            &self.interaction.token,
+            &(InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(data.into_ird()),
+            })
+        ).await?;
+
+        Ok(())
+    }
 }
 
 impl<'a> Deref for CommandInteractionContext<'a> {
@@ -0,0 +1,251 @@
+//! Audio files from a local directory, indexed by their tags so they can be searched.
+
+use std::{ fs::{ self, File }, path::{ Path, PathBuf }, time::Duration };
+
+use anyhow::{ Context, Result };
+use songbird::input::{
+    codecs::get_probe,
+    core::{
+        formats::FormatOptions,
+        io::{ MediaSource, MediaSourceStream },
+        meta::{ MetadataOptions, MetadataRevision, StandardTagKey },
+        probe::Hint,
+    },
+    AudioStream,
+    AudioStreamError,
+    AuxMetadata,
+    Compose,
+};
+
+/// The kinds of files the library picks up.
+const EXTENSIONS: [&str; 4] = ["mp3", "flac", "m4a", "ogg"];
+
+/// A track from the library.
+#[derive(Debug, Clone)]
+pub(crate) struct LocalAudio {
+    id: String,
+    path: PathBuf,
+    metadata: AuxMetadata,
+}
+
+impl LocalAudio {
+    /// Looks like `local:<n>`. Only valid until the library is indexed again.
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn metadata(&self) -> &AuxMetadata {
+        &self.metadata
+    }
+
+    /// The title and artist, for lists.
+    pub(crate) fn describe(&self) -> String {
+        format!(
+            "{} - {}",
+            self.metadata.track.as_deref().unwrap_or("Unknown"),
+            self.metadata.artist.as_deref().unwrap_or("Unknown")
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Compose for LocalAudio {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let file = File::open(&self.path).map_err(|e| AudioStreamError::Fail(e.into()))?;
+
+        Ok(AudioStream {
+            input: Box::new(file),
+            hint: Some(hint(&self.path)),
+        })
+    }
+
+    async fn create_async(
+        &mut self
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        self.create()
+    }
+
+    fn should_create_async(&self) -> bool {
+        false
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        Ok(self.metadata.clone())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Library {
+    tracks: Vec<LocalAudio>,
+}
+
+impl Library {
+    pub(crate) fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Reads the tags of every audio file under `root`. This blocks for a while with big
+    /// libraries.
+    pub(crate) fn index(root: &Path) -> Result<Self> {
+        let mut paths = vec![];
+        collect(root, &mut paths).with_context(|| format!("Failed to read {}", root.display()))?;
+        paths.sort();
+
+        let tracks = paths
+            .into_iter()
+            .enumerate()
+            .map(|(n, path)| {
+                let metadata = read_metadata(&path).unwrap_or_else(|e| {
+                    tracing::warn!(?e, "Failed to read the tags of {}", path.display());
+                    AuxMetadata::default()
+                });
+                let metadata = AuxMetadata {
+                    // files without tags are still worth finding by name
+                    track: metadata.track.or_else(|| {
+                        path.file_stem().map(|stem| stem.to_string_lossy().into_owned())
+                    }),
+                    ..metadata
+                };
+
+                LocalAudio { id: format!("local:{n}"), path, metadata }
+            })
+            .collect();
+
+        Ok(Self { tracks })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<&LocalAudio> {
+        let n = id.strip_prefix("local:")?.parse::<usize>().ok()?;
+        self.tracks.get(n)
+    }
+
+    /// Tracks whose title, artist, album or file name have every word of `query`, the best
+    /// matches first.
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<&LocalAudio> {
+        let query = query.trim().to_lowercase();
+        let words = query.split_whitespace().collect::<Vec<_>>();
+
+        let mut matches = self.tracks
+            .iter()
+            .filter_map(|track| {
+                let title = track.metadata.track.as_deref().unwrap_or_default().to_lowercase();
+                let haystack = format!(
+                    "{title} {} {} {}",
+                    track.metadata.artist.as_deref().unwrap_or_default().to_lowercase(),
+                    track.metadata.album.as_deref().unwrap_or_default().to_lowercase(),
+                    track.path.file_name().unwrap_or_default().to_string_lossy().to_lowercase()
+                );
+                if !words.iter().all(|word| haystack.contains(word)) {
+                    return None;
+                }
+
+                // titles that start with the query rank first, then titles that contain it
+                let rank = if title.starts_with(&query) {
+                    0
+                } else if title.contains(&query) {
+                    1
+                } else {
+                    2
+                };
+                Some((rank, track))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(rank, _)| *rank);
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, track)| track)
+            .collect()
+    }
+}
+
+/// Adds the audio files under `dir` to `paths`, going into subdirectories but not symlinked ones.
+fn collect(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            // symlinked directories can point back up and send this around in circles
+            if entry.file_type()?.is_symlink() {
+                tracing::debug!("Skipping the symlinked directory {}", path.display());
+                continue;
+            }
+            if let Err(e) = collect(&path, paths) {
+                tracing::warn!(?e, "Skipping {}", path.display());
+            }
+        } else if
+            path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn hint(path: &Path) -> Hint {
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+    hint
+}
+
+fn read_metadata(path: &Path) -> Result<AuxMetadata> {
+    let file = File::open(path)?;
+    let mut probed = get_probe().format(
+        &hint(path),
+        MediaSourceStream::new(Box::new(file), Default::default()),
+        &FormatOptions::default(),
+        &MetadataOptions::default()
+    )?;
+
+    let mut metadata = AuxMetadata::default();
+    // tags can be in front of the container (like ID3) or inside it
+    if let Some(mut probed_metadata) = probed.metadata.get()
+        && let Some(revision) = probed_metadata.skip_to_latest()
+    {
+        read_tags(revision, &mut metadata);
+    }
+    if let Some(revision) = probed.format.metadata().skip_to_latest() {
+        read_tags(revision, &mut metadata);
+    }
+
+    if let Some(track) = probed.format.default_track()
+        && let (Some(time_base), Some(frames)) = (
+            track.codec_params.time_base,
+            track.codec_params.n_frames,
+        )
+    {
+        let time = time_base.calc_time(frames);
+        metadata.duration = Some(
+            Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+        );
+    }
+
+    Ok(metadata)
+}
+
+fn read_tags(revision: &MetadataRevision, metadata: &mut AuxMetadata) {
+    for tag in revision.tags() {
+        let value = Some(tag.value.to_string());
+        match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => metadata.track = value,
+            Some(StandardTagKey::Artist) => metadata.artist = value,
+            Some(StandardTagKey::Album) => metadata.album = value,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::util::parse_timestamp;
+
+/// Something a YouTube or YouTube Music link points to.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Link {
+    Video {
+        id: String,
+        /// Where to start playing, from the `t=` or `start=` parameter.
+        start: Option<Duration>,
+    },
+    Playlist {
+        id: String,
+    },
+    Album {
+        /// The album's browse id, like `MPREb_...`.
+        id: String,
+    },
+}
+
+/// Parses a YouTube or YouTube Music link, or a bare video id. Returns `None` for anything
+/// else, like search queries.
+pub(crate) fn parse(query: &str) -> Option<Link> {
+    let query = query.trim();
+    if is_video_id(query) {
+        return Some(Link::Video { id: query.to_string(), start: None });
+    }
+
+    let url = Url::parse(query).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.").trim_start_matches("m.");
+
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let start = param("t").or_else(|| param("start")).and_then(|t| parse_timestamp(&t));
+
+    let id = match host {
+        "youtu.be" => url.path_segments()?.next()?.to_string(),
+        "youtube.com" | "music.youtube.com" => {
+            match url.path() {
+                "/watch" => param("v")?,
+                "/playlist" => {
+                    return param("list")
+                        .filter(|id| !id.is_empty())
+                        .map(|id| Link::Playlist { id });
+                }
+                path if path.starts_with("/browse/") => {
+                    let id = path.trim_start_matches("/browse/");
+                    return if id.starts_with("MPREb_") {
+                        Some(Link::Album { id: id.to_string() })
+                    } else {
+                        // playlists can be browsed too, with a `VL` prefix
+                        id.strip_prefix("VL").map(|id| Link::Playlist { id: id.to_string() })
+                    };
+                }
+                path => {
+                    // `/shorts/<id>`, `/live/<id>` and `/embed/<id>`
+                    let (kind, id) = path.trim_start_matches('/').split_once('/')?;
+                    if !matches!(kind, "shorts" | "live" | "embed") {
+                        return None;
+                    }
+                    id.to_string()
+                }
+            }
+        }
+        _ => {
+            return None;
+        }
+    };
+    if id.is_empty() {
+        return None;
+    }
+
+    Some(Link::Video { id, start })
+}
+
+/// Whether `query` looks like a video id, like `dQw4w9WgXcQ`.
+///
+/// Ids are 11 characters long, which plenty of plain words are too, so this also wants a digit,
+/// `-` or `_` in there.
+pub(crate) fn is_video_id(query: &str) -> bool {
+    query.len() == 11 &&
+        query.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') &&
+        query.chars().any(|c| c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(id: &str, start: Option<u64>) -> Option<Link> {
+        Some(Link::Video { id: id.to_string(), start: start.map(Duration::from_secs) })
+    }
+
+    #[test]
+    fn parses_video_links() {
+        assert_eq!(parse("https://youtu.be/dQw4w9WgXcQ"), video("dQw4w9WgXcQ", None));
+        assert_eq!(
+            parse("https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            video("dQw4w9WgXcQ", None)
+        );
+        assert_eq!(parse("https://m.youtube.com/watch?v=dQw4w9WgXcQ"), video("dQw4w9WgXcQ", None));
+        assert_eq!(
+            parse("https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=RDAMVM"),
+            video("dQw4w9WgXcQ", None)
+        );
+        assert_eq!(parse("https://youtube.com/shorts/dQw4w9WgXcQ"), video("dQw4w9WgXcQ", None));
+        assert_eq!(parse("https://youtube.com/live/dQw4w9WgXcQ"), video("dQw4w9WgXcQ", None));
+        assert_eq!(parse("https://youtube.com/embed/dQw4w9WgXcQ"), video("dQw4w9WgXcQ", None));
+    }
+
+    #[test]
+    fn parses_start_times() {
+        assert_eq!(parse("https://youtu.be/dQw4w9WgXcQ?t=90"), video("dQw4w9WgXcQ", Some(90)));
+        assert_eq!(
+            parse("https://youtube.com/watch?v=dQw4w9WgXcQ&t=1m30s"),
+            video("dQw4w9WgXcQ", Some(90))
+        );
+        assert_eq!(
+            parse("https://youtube.com/embed/dQw4w9WgXcQ?start=42"),
+            video("dQw4w9WgXcQ", Some(42))
+        );
+        assert_eq!(parse("https://youtu.be/dQw4w9WgXcQ?t=soon"), video("dQw4w9WgXcQ", None));
+    }
+
+    #[test]
+    fn parses_playlists_and_albums() {
+        assert_eq!(
+            parse("https://youtube.com/playlist?list=PLabc123"),
+            Some(Link::Playlist { id: "PLabc123".to_string() })
+        );
+        assert_eq!(parse("https://youtube.com/playlist?list="), None);
+        assert_eq!(
+            parse("https://music.youtube.com/browse/VLPLabc123"),
+            Some(Link::Playlist { id: "PLabc123".to_string() })
+        );
+        assert_eq!(
+            parse("https://music.youtube.com/browse/MPREb_abc123"),
+            Some(Link::Album { id: "MPREb_abc123".to_string() })
+        );
+        assert_eq!(parse("https://music.youtube.com/browse/UCabc123"), None);
+    }
+
+    #[test]
+    fn parses_bare_video_ids() {
+        assert_eq!(parse("dQw4w9WgXcQ"), video("dQw4w9WgXcQ", None));
+        assert_eq!(parse("  dQw4w9WgXcQ "), video("dQw4w9WgXcQ", None));
+        assert_eq!(parse("lofi-hiphop"), video("lofi-hiphop", None));
+    }
+
+    #[test]
+    fn ignores_everything_else() {
+        // 11 letters, but no digit, `-` or `_`
+        assert_eq!(parse("strawberrys"), None);
+        assert_eq!(parse("never gonna give you up"), None);
+        assert_eq!(parse("https://example.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(parse("https://youtube.com/watch"), None);
+        assert_eq!(parse("https://youtube.com/channel/UCabc123"), None);
+        assert_eq!(parse("https://youtu.be/"), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! Track lyrics from YouTube Music, cached so paging through them doesn't fetch them again.
+
+use std::{ sync::Arc, time::Duration };
+
+use anyhow::Result;
+use dashmap::DashMap;
+use tokio::time::Instant;
+
+use crate::innertube::InnerTube;
+
+/// How long fetched lyrics are reused for.
+const CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// The most tracks kept in the cache.
+const CACHE_SIZE: usize = 100;
+
+#[derive(Debug)]
+pub(crate) struct Lyrics {
+    /// The title of the track.
+    pub(crate) title: String,
+    pub(crate) lines: Vec<String>,
+    /// When each line starts, if the lyrics are timed.
+    pub(crate) times: Option<Vec<Duration>>,
+    /// Where the lyrics are from.
+    pub(crate) source: String,
+}
+
+impl Lyrics {
+    /// Reads lyrics. They're usually plain text, but count as timed if every line starts with an
+    /// LRC timestamp like `[01:23.45]`.
+    fn parse(title: String, body: &str, source: String) -> Self {
+        let lines = body
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let timed = lines
+            .iter()
+            .map(|line| parse_lrc_line(line))
+            .collect::<Option<Vec<_>>>()
+            .filter(|timed| !timed.is_empty());
+        match timed {
+            Some(timed) => {
+                let (times, lines) = timed.into_iter().unzip();
+                Self { title, lines, times: Some(times), source }
+            }
+            None => {
+                Self {
+                    title,
+                    lines: lines.into_iter().map(str::to_string).collect(),
+                    times: None,
+                    source,
+                }
+            }
+        }
+    }
+
+    /// The line being sung at `position`. Untimed lyrics are spread evenly over `duration`,
+    /// which is only a guess.
+    pub(crate) fn line_at(&self, position: Duration, duration: Option<Duration>) -> Option<usize> {
+        if self.lines.is_empty() {
+            return None;
+        }
+
+        match &self.times {
+            Some(times) => times.iter().rposition(|time| *time <= position),
+            None => {
+                let duration = duration.filter(|duration| !duration.is_zero())?;
+                let progress = position.as_secs_f64() / duration.as_secs_f64();
+                Some(((progress * (self.lines.len() as f64)) as usize).min(self.lines.len() - 1))
+            }
+        }
+    }
+}
+
+fn parse_lrc_line(line: &str) -> Option<(Duration, String)> {
+    let (timestamp, text) = line.strip_prefix('[')?.split_once(']')?;
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let minutes = Duration::from_secs(minutes.parse::<u64>().ok()?.checked_mul(60)?);
+    let time = minutes.checked_add(Duration::try_from_secs_f64(seconds.parse().ok()?).ok()?)?;
+
+    Some((time, text.trim().to_string()))
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct LyricsCache {
+    /// Lyrics by video id. `None` if the track has none.
+    cache: DashMap<String, (Instant, Option<Arc<Lyrics>>)>,
+}
+
+impl LyricsCache {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The lyrics of a track, fetched if they aren't cached.
+    pub(crate) async fn get(&self, video_id: &str) -> Result<Option<Arc<Lyrics>>> {
+        if let Some(lyrics) = self.cache
+            .get(video_id)
+            .filter(|rf| rf.0.elapsed() < CACHE_TTL)
+            .map(|rf| rf.1.clone())
+        {
+            return Ok(lyrics);
+        }
+
+        let lyrics = InnerTube::new()
+            .lyrics(video_id).await?
+            .map(|(title, lyrics)| Arc::new(Lyrics::parse(title, &lyrics.body, lyrics.footer)));
+
+        if self.cache.len() >= CACHE_SIZE {
+            self.cache.retain(|_, (at, _)| at.elapsed() < CACHE_TTL);
+            if self.cache.len() >= CACHE_SIZE {
+                self.cache.clear();
+            }
+        }
+        self.cache.insert(video_id.to_string(), (Instant::now(), lyrics.clone()));
+
+        Ok(lyrics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> Lyrics {
+        Lyrics::parse("Title".to_string(), body, "Source".to_string())
+    }
+
+    #[test]
+    fn parses_plain_lyrics() {
+        let lyrics = parse("First line\n\n  Second line  \n");
+        assert_eq!(lyrics.lines, ["First line", "Second line"]);
+        assert_eq!(lyrics.times, None);
+    }
+
+    #[test]
+    fn parses_timed_lyrics() {
+        let lyrics = parse("[00:01.50] First line\n[01:02.00]Second line");
+        assert_eq!(lyrics.lines, ["First line", "Second line"]);
+        assert_eq!(
+            lyrics.times,
+            Some(vec![Duration::from_millis(1500), Duration::from_secs(62)])
+        );
+    }
+
+    #[test]
+    fn needs_every_line_timed() {
+        let lyrics = parse("[00:01.50] First line\nSecond line");
+        assert_eq!(lyrics.lines, ["[00:01.50] First line", "Second line"]);
+        assert_eq!(lyrics.times, None);
+    }
+
+    #[test]
+    fn rejects_bad_timestamps() {
+        assert_eq!(parse_lrc_line("[99999999999999999999:00.00] Line"), None);
+        assert_eq!(parse_lrc_line("[307445734561825861:00.00] Line"), None);
+        assert_eq!(parse_lrc_line("[00:-1.00] Line"), None);
+        assert_eq!(parse_lrc_line("[00:xx] Line"), None);
+        assert_eq!(parse_lrc_line("[0001.00] Line"), None);
+    }
+
+    #[test]
+    fn finds_timed_lines() {
+        let lyrics = parse("[00:05.00] One\n[00:10.00] Two\n[00:20.00] Three");
+        assert_eq!(lyrics.line_at(Duration::from_secs(1), None), None);
+        assert_eq!(lyrics.line_at(Duration::from_secs(5), None), Some(0));
+        assert_eq!(lyrics.line_at(Duration::from_secs(15), None), Some(1));
+        assert_eq!(lyrics.line_at(Duration::from_secs(600), None), Some(2));
+    }
+
+    #[test]
+    fn spreads_untimed_lines() {
+        let lyrics = parse("One\nTwo\nThree\nFour");
+        let duration = Some(Duration::from_secs(40));
+        assert_eq!(lyrics.line_at(Duration::ZERO, duration), Some(0));
+        assert_eq!(lyrics.line_at(Duration::from_secs(25), duration), Some(2));
+        assert_eq!(lyrics.line_at(Duration::from_secs(60), duration), Some(3));
+        assert_eq!(lyrics.line_at(Duration::from_secs(5), None), None);
+        assert_eq!(lyrics.line_at(Duration::from_secs(5), Some(Duration::ZERO)), None);
+        assert_eq!(parse("").line_at(Duration::ZERO, duration), None);
+    }
+}
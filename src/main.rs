@@ -5,15 +5,36 @@ use tracing::instrument;
 use twilight_gateway::{ Event, EventTypeFlags, Intents, Shard, ShardId, StreamExt };
 use twilight_http::Client as HttpClient;
 use twilight_interactions::command::CreateCommand;
-use twilight_model::application::{ command::Command, interaction::InteractionData };
-
-use crate::{ commands::BerdCommands, state::State };
-
+use twilight_model::{
+    application::{ command::Command, interaction::{ InteractionData, InteractionType } },
+    gateway::payload::incoming::GuildCreate,
+};
+
+use crate::{
+    commands::BerdCommands,
+    config::Config,
+    library::Library,
+    settings::Settings,
+    state::State,
+};
+
+mod audio;
+mod auto_leave;
 mod innertube;
 mod interaction_context;
 mod commands;
 mod cache;
+mod config;
+mod dsp;
+mod library;
+mod links;
+mod lyrics;
+mod player;
+mod settings;
 mod state;
+mod stream;
+mod suggestions;
+mod util;
 
 #[tokio::main]
 #[instrument]
@@ -23,6 +44,27 @@ async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv_override().ok();
 
     let token = dotenvy::var("BERD_DISCORD_TOKEN")?;
+    let config = Config::from_env()?;
+    let settings = Settings::load(config.settings_path.clone())?;
+    let library = match config.music_dir.clone() {
+        Some(dir) => {
+            let library = tokio::task
+                ::spawn_blocking(move || Library::index(&dir)).await
+                .unwrap_or_else(|e| Err(e.into()));
+            match library {
+                Ok(library) => {
+                    tracing::info!("Indexed {} local tracks", library.len());
+                    library
+                }
+                // the bot is still useful without local music
+                Err(e) => {
+                    tracing::warn!(?e, "Failed to index the local music library");
+                    Library::empty()
+                }
+            }
+        }
+        None => Library::empty(),
+    };
 
     let http = HttpClient::new(token.clone());
     let user_id = http.current_user().await?.model().await?.id;
@@ -33,10 +75,14 @@ async fn main() -> anyhow::Result<()> {
         ic.set_global_commands(&commands).await?;
     }
 
-    let intents = Intents::GUILD_MESSAGES | Intents::GUILD_VOICE_STATES | Intents::MESSAGE_CONTENT;
-    let config = twilight_gateway::Config::new(token, intents);
+    let intents =
+        Intents::GUILDS |
+        Intents::GUILD_MESSAGES |
+        Intents::GUILD_VOICE_STATES |
+        Intents::MESSAGE_CONTENT;
+    let gateway_config = twilight_gateway::Config::new(token, intents);
     let shards: Vec<Shard> = twilight_gateway
-        ::create_recommended(&http, config, |_, builder| builder.build()).await?
+        ::create_recommended(&http, gateway_config, |_, builder| builder.build()).await?
         .collect();
 
     let senders = TwilightMap::new(
@@ -47,7 +93,7 @@ async fn main() -> anyhow::Result<()> {
     );
     let songbird = Songbird::twilight(Arc::new(senders), user_id);
 
-    let state = Arc::new(State::new(http, songbird));
+    let state = Arc::new(State::new(http, songbird, config, settings, library));
 
     let mut set = tokio::task::JoinSet::new();
     for shard in shards {
@@ -83,24 +129,49 @@ async fn handle_event(shard: &ShardId, event: Event, state: Arc<State>) {
         Event::Ready(_) => {
             tracing::info!("Shard {} is ready", shard);
         }
+        Event::GuildCreate(gc) => {
+            // voice states sent with the guild don't carry the guild id
+            if let GuildCreate::Available(guild) = *gc {
+                for mut vs in guild.voice_states {
+                    vs.guild_id = Some(guild.id);
+                    state.cache.record_voice_state(vs.user_id, vs);
+                }
+            }
+        }
         Event::VoiceStateUpdate(vsu) => {
             let vsu = *vsu;
             let vs = vsu.0;
             let user_id = vs.user_id;
+            let guild = vs.guild_id;
             state.cache.record_voice_state(user_id, vs);
+
+            if let Some(guild) = guild {
+                auto_leave::check_alone(state, guild).await;
+            }
         }
         Event::InteractionCreate(icre) => {
             let mut interaction = (*icre).0;
             let data = interaction.data.take().unwrap();
 
-            if let InteractionData::ApplicationCommand(cmd) = data {
-                if let Err(e) = BerdCommands::run(interaction, *cmd, state).await {
-                    tracing::error!(?e);
+            let result = match data {
+                // autocomplete requests carry command data too, told apart by their kind
+                InteractionData::ApplicationCommand(cmd) if
+                    interaction.kind == InteractionType::ApplicationCommandAutocomplete
+                => {
+                    BerdCommands::run_autocomplete(interaction, *cmd, state).await
                 }
+                InteractionData::ApplicationCommand(cmd) => {
+                    BerdCommands::run(interaction, *cmd, state).await
+                }
+                InteractionData::MessageComponent(component) => {
+                    BerdCommands::run_component(interaction, *component, state).await
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                tracing::error!(?e);
             }
         }
-        _ => {
-            return;
-        }
+        _ => {}
     }
 }
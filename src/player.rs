@@ -0,0 +1,414 @@
+use std::{ collections::HashSet, sync::Arc, time::Duration };
+
+use anyhow::Context;
+use async_trait::async_trait;
+use songbird::{
+    input::Input,
+    tracks::{ PlayMode, Track, TrackHandle },
+    Call,
+    Event as SongbirdEvent,
+    EventContext,
+    EventHandler as SongbirdEventHandler,
+    TrackEvent,
+};
+use twilight_model::id::{ marker::GuildMarker, Id };
+
+use crate::{
+    auto_leave::{ self, LeaveReason },
+    dsp::FilteredAudio,
+    innertube::InnerTube,
+    state::{ QueuedTrack, State },
+};
+
+/// How long before the end of a track the next one is preloaded. This leaves time to get it
+/// ready before the longest crossfade starts.
+const PRELOAD_AHEAD: Duration = Duration::from_secs(15);
+
+/// How often the volumes change during a crossfade.
+const CROSSFADE_STEP: Duration = Duration::from_millis(50);
+
+/// How often tracks check whether it's time to preload.
+const PRELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many tracks in a row can fail to play before Berd gives up on the queue.
+const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// How many songs autoplay queues at once.
+const AUTOPLAY_BATCH: usize = 5;
+
+/// Starts playing `track` on the call, and makes it the guild's current track.
+pub(crate) fn play_track(
+    call: &mut Call,
+    guild: Id<GuildMarker>,
+    track: QueuedTrack,
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    let volume = state.track_volume(&guild, &track.audio);
+    start_track(call, guild, track, state, volume)
+}
+
+/// Like [`play_track`], but the track starts at the given volume.
+fn start_track(
+    call: &mut Call,
+    guild: Id<GuildMarker>,
+    track: QueuedTrack,
+    state: Arc<State>,
+    volume: f32
+) -> anyhow::Result<()> {
+    auto_leave::cancel(&state, guild, LeaveReason::Idle);
+
+    let preloaded = state
+        .take_preloaded(&guild, &track)
+        .filter(|handle| handle.set_volume(volume).is_ok() && handle.play().is_ok());
+    let handle = match preloaded {
+        // its events were added when it was preloaded
+        Some(handle) => handle,
+        None => {
+            let handle = call.play(Track::new(input(&state, guild, &track)).volume(volume));
+            add_track_handle_events(&handle, guild, state.clone())?;
+            if let Some(start) = track.start {
+                // the seek is applied once the track is ready, there's no need to wait for it
+                drop(handle.seek(start));
+            }
+            handle
+        }
+    };
+    state.record_history(guild, &track);
+    state.put_handle(guild, handle, track);
+
+    Ok(())
+}
+
+/// The track's audio, through the guild's filters.
+fn input(state: &State, guild: Id<GuildMarker>, track: &QueuedTrack) -> Input {
+    FilteredAudio::new(track.audio.clone(), state.effects(guild)).into()
+}
+
+/// Makes changed filters apply to what's playing and what's next.
+pub(crate) async fn effects_changed(
+    state: &Arc<State>,
+    guild: Id<GuildMarker>,
+    was_neutral: bool
+) -> anyhow::Result<()> {
+    // the preloaded track was prepared with the old filters
+    state.drop_preload(&guild);
+
+    // tracks without filters aren't decoded by Berd, so they can't pick up new ones
+    let is_neutral = state.effects(guild).read().is_ok_and(|effects| effects.is_neutral());
+    if was_neutral && !is_neutral {
+        restart_current(state, guild).await?;
+    }
+
+    Ok(())
+}
+
+/// Plays the current track again from where it is, so it picks up filters that can only be
+/// applied from the start of a stream.
+async fn restart_current(
+    state: &Arc<State>,
+    guild: Id<GuildMarker>
+) -> anyhow::Result<()> {
+    let Some((handle, current)) = state.guild_data
+        .get(&guild)
+        .map(|rf| (rf.handle.clone(), rf.current.clone())) else {
+        return Ok(());
+    };
+    let Some(birdx) = state.songbird.get(guild) else {
+        return Ok(());
+    };
+    let info = handle.get_info().await?;
+
+    let mut call = birdx.lock().await;
+    let track = current.with_start(Some(info.position));
+    start_track(&mut call, guild, track, state.clone(), info.volume)?;
+    if info.playing == PlayMode::Pause
+        && let Some(rf) = state.guild_data.get(&guild)
+    {
+        rf.handle.pause()?;
+    }
+    handle.stop()?;
+
+    Ok(())
+}
+
+struct TrackHandleEvents {
+    state: Arc<State>,
+    guild: Id<GuildMarker>,
+    /// Whether this handles [`TrackEvent::Error`] rather than [`TrackEvent::End`].
+    failed: bool,
+}
+
+#[async_trait]
+impl SongbirdEventHandler for TrackHandleEvents {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<SongbirdEvent> {
+        tracing::info!("Song finished");
+
+        // ignore tracks that were replaced or stopped along with their session
+        if let EventContext::Track([(info, ended), ..]) = ctx {
+            if !self.state.is_current_handle(&self.guild, ended) {
+                return None;
+            }
+            if self.failed {
+                tracing::error!(playing = ?info.playing, "Failed to play a track");
+            }
+        }
+
+        let failures = self.state.record_outcome(&self.guild, self.failed);
+        if failures >= MAX_CONSECUTIVE_FAILURES {
+            tracing::error!("{} tracks in a row failed to play, stopping", failures);
+            self.state.remove_guild_data(&self.guild);
+            auto_leave::schedule(self.state.clone(), self.guild, LeaveReason::Idle);
+            return None;
+        }
+
+        let next = match self.state.next_track(&self.guild) {
+            Some(track) => Some(track),
+            None if self.state.settings.get(&self.guild).autoplay => {
+                let track = autoplay(&self.state, self.guild).await;
+                // the session might have been stopped while the radio loaded
+                if let EventContext::Track([(_, ended), ..]) = ctx
+                    && !self.state.is_current_handle(&self.guild, ended)
+                {
+                    return None;
+                }
+                track
+            }
+            None => None,
+        };
+
+        if let Some(track) = next {
+            let birdx = self.state.songbird.get_or_insert(self.guild);
+            tracing::info!("Next song: {:?}", track.audio);
+
+            let mut call = birdx.lock().await;
+            if let Err(e) = play_track(&mut call, self.guild, track, self.state.clone()) {
+                tracing::error!(?e, "Failed to play the next track");
+            }
+        } else {
+            tracing::info!("Removed guild data: {}", &self.guild);
+            self.state.remove_guild_data(&self.guild);
+            auto_leave::schedule(self.state.clone(), self.guild, LeaveReason::Idle);
+        }
+
+        None
+    }
+}
+
+/// Sets the volume once a track's stream is resolved, as that's when its loudness is known.
+struct NormalizeEvents {
+    state: Arc<State>,
+    guild: Id<GuildMarker>,
+}
+
+#[async_trait]
+impl SongbirdEventHandler for NormalizeEvents {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<SongbirdEvent> {
+        let EventContext::Track([(_, handle), ..]) = ctx else {
+            return None;
+        };
+        // preloaded tracks get their volume when they start
+        if let Some((current, volume)) = self.state.current_volume(&self.guild)
+            && current.uuid() == handle.uuid()
+        {
+            drop(handle.set_volume(volume));
+        }
+
+        None
+    }
+}
+
+/// Checks how much of the current track is left, to preload the next one and crossfade into
+/// it near the end.
+struct PreloadEvents {
+    state: Arc<State>,
+    guild: Id<GuildMarker>,
+}
+
+#[async_trait]
+impl SongbirdEventHandler for PreloadEvents {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<SongbirdEvent> {
+        let EventContext::Track([(info, handle), ..]) = ctx else {
+            return None;
+        };
+        if !self.state.is_current_handle(&self.guild, handle) {
+            return None;
+        }
+
+        let duration = self.state.guild_data
+            .get(&self.guild)
+            .and_then(|rf| rf.current.audio.metadata().duration)?;
+        let duration = self.state.playback_duration(self.guild, duration);
+        let remaining = duration.saturating_sub(info.position);
+        if remaining > PRELOAD_AHEAD {
+            return None;
+        }
+
+        if let Some(track) = self.state.claim_preload(&self.guild) {
+            // don't hold up the call's other events, like the end of this track
+            let (state, guild) = (self.state.clone(), self.guild);
+            tokio::spawn(async move { preload(&state, guild, track).await });
+            return None;
+        }
+
+        let crossfade = Duration::from_secs(self.state.settings.get(&self.guild).crossfade.into());
+        if !crossfade.is_zero() && remaining <= crossfade && self.state.preload_ready(&self.guild) {
+            start_crossfade(&self.state, self.guild, (*handle).clone(), remaining).await;
+        }
+
+        None
+    }
+}
+
+/// Moves on to the next track while `outgoing` is still playing, then fades one into the other.
+async fn start_crossfade(
+    state: &Arc<State>,
+    guild: Id<GuildMarker>,
+    outgoing: TrackHandle,
+    fade: Duration
+) {
+    let Some(birdx) = state.songbird.get(guild) else {
+        return;
+    };
+    // each track keeps its own loudness while they overlap
+    let outgoing_audio = state.guild_data.get(&guild).map(|rf| rf.current.audio.clone());
+    let Some(outgoing_audio) = outgoing_audio else {
+        return;
+    };
+    let Some(track) = state.next_track(&guild) else {
+        return;
+    };
+    tracing::info!("Crossfading into {}", track.audio.id());
+
+    // once the next track is current, the end of `outgoing` is ignored
+    let result = {
+        let mut call = birdx.lock().await;
+        start_track(&mut call, guild, track, state.clone(), 0.0)
+    };
+    if let Err(e) = result {
+        tracing::error!(?e, "Failed to play the next track");
+        return;
+    }
+    let incoming = state.guild_data
+        .get(&guild)
+        .map(|rf| (rf.handle.clone(), rf.current.audio.clone()));
+    let Some((incoming, incoming_audio)) = incoming else {
+        return;
+    };
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let steps = (fade.as_millis() / CROSSFADE_STEP.as_millis()).max(1) as u32;
+        let mut interval = tokio::time::interval(fade / steps);
+        for step in 1..=steps {
+            interval.tick().await;
+
+            // the volume might change during the fade
+            let outgoing_volume = state.track_volume(&guild, &outgoing_audio);
+            let incoming_volume = state.track_volume(&guild, &incoming_audio);
+            let progress = (step as f32) / (steps as f32);
+            drop(outgoing.set_volume(outgoing_volume * (1.0 - progress)));
+            if incoming.set_volume(incoming_volume * progress).is_err() {
+                break;
+            }
+        }
+        drop(outgoing.stop());
+    });
+}
+
+/// Adds `track` to the call paused, and gets it ready to play.
+async fn preload(state: &Arc<State>, guild: Id<GuildMarker>, track: QueuedTrack) {
+    let id = track.audio.id().to_string();
+    tracing::info!("Preloading {}", id);
+
+    let Some(birdx) = state.songbird.get(guild) else {
+        state.cancel_preload(&guild, &id);
+        return;
+    };
+    let handle = {
+        let mut call = birdx.lock().await;
+        let volume = state.track_volume(&guild, &track.audio);
+        call.play(Track::new(input(state, guild, &track)).volume(volume).pause())
+    };
+
+    let ready = async {
+        add_track_handle_events(&handle, guild, state.clone())?;
+        handle.make_playable_async().await?;
+        anyhow::Ok(())
+    };
+    if let Err(e) = ready.await {
+        tracing::warn!(?e, "Failed to preload {}", id);
+        drop(handle.stop());
+        state.cancel_preload(&guild, &id);
+        return;
+    }
+
+    if !state.finish_preload(&guild, &id, handle.clone()) {
+        drop(handle.stop());
+    }
+}
+
+/// Queues a few songs from the radio of the last played track, returning the first one.
+async fn autoplay(state: &State, guild: Id<GuildMarker>) -> Option<QueuedTrack> {
+    let (seed, requester, exclude) = {
+        let history = state.history.get(&guild)?;
+        // local tracks have no radio, so go by the last song from YouTube
+        let (seed, requester) = history
+            .iter()
+            .rev()
+            .find_map(|entry| Some((entry.audio.youtube_id()?.to_string(), entry.requester)))?;
+        let exclude = history
+            .iter()
+            .map(|entry| entry.audio.id().to_string())
+            .collect::<HashSet<_>>();
+        (seed, requester, exclude)
+    };
+
+    tracing::info!("Autoplaying from {}", seed);
+    let tracks = match InnerTube::new().radio(&seed, &exclude, AUTOPLAY_BATCH).await {
+        Ok(tracks) => tracks,
+        Err(e) => {
+            tracing::error!(?e, "Failed to get the radio for autoplay");
+            return None;
+        }
+    };
+
+    // autoplayed songs are credited to whoever asked for the song they came from
+    for audio in tracks {
+        state.add_track(&guild, QueuedTrack::new(audio, requester));
+    }
+
+    // songs queued while the radio loaded go first
+    state.next_track(&guild)
+}
+
+fn add_track_handle_events(
+    handle: &TrackHandle,
+    guild: Id<GuildMarker>,
+    state: Arc<State>
+) -> anyhow::Result<()> {
+    // a track that fails to start (say, its stream can't be resolved) errors instead of ending
+    for event in [TrackEvent::End, TrackEvent::Error] {
+        let event_handler = TrackHandleEvents {
+            state: state.clone(),
+            guild,
+            failed: matches!(event, TrackEvent::Error),
+        };
+        handle
+            .add_event(SongbirdEvent::Track(event), event_handler)
+            .context("Failed to register event handler for track")?;
+    }
+    handle
+        .add_event(SongbirdEvent::Track(TrackEvent::Playable), NormalizeEvents {
+            state: state.clone(),
+            guild,
+        })
+        .context("Failed to register normalization handler for track")?;
+    handle
+        .add_event(
+            SongbirdEvent::Periodic(PRELOAD_CHECK_INTERVAL, None),
+            PreloadEvents { state, guild }
+        )
+        .context("Failed to register preload handler for track")?;
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Per-guild settings that survive restarts, saved as JSON.
+
+use std::{ collections::HashMap, fs, io::ErrorKind, path::{ Path, PathBuf } };
+
+use anyhow::{ Context, Result };
+use dashmap::DashMap;
+use serde::{ Deserialize, Serialize };
+use tokio::sync::Mutex;
+use twilight_model::id::{ marker::GuildMarker, Id };
+
+use crate::dsp::EQ_BANDS;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GuildSettings {
+    /// The volume new sessions start at, in percent.
+    pub(crate) volume: u8,
+    /// Queue similar songs when the queue runs dry.
+    pub(crate) autoplay: bool,
+    /// How many seconds tracks fade into each other for. `0` turns crossfading off.
+    pub(crate) crossfade: u8,
+    /// The gain of each equalizer band, in dB.
+    pub(crate) eq: [i8; EQ_BANDS],
+    /// Play tracks at the same loudness, using the loudness YouTube reports.
+    pub(crate) normalize: bool,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            volume: 100,
+            autoplay: false,
+            crossfade: 0,
+            eq: [0; EQ_BANDS],
+            normalize: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Settings {
+    path: PathBuf,
+    guilds: DashMap<Id<GuildMarker>, GuildSettings>,
+    /// Held while saving, so saves can't share the temporary file or land out of order.
+    save_lock: Mutex<()>,
+}
+
+impl Settings {
+    /// Loads the settings at `path`, if the file exists.
+    pub(crate) fn load(path: PathBuf) -> Result<Self> {
+        let guilds = match fs::read(&path) {
+            Ok(data) => {
+                serde_json
+                    ::from_slice::<HashMap<Id<GuildMarker>, GuildSettings>>(&data)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?
+                    .into_iter()
+                    .collect()
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => DashMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+
+        Ok(Self { path, guilds, save_lock: Mutex::new(()) })
+    }
+
+    pub(crate) fn get(&self, guild: &Id<GuildMarker>) -> GuildSettings {
+        self.guilds
+            .get(guild)
+            .map(|rf| rf.clone())
+            .unwrap_or_default()
+    }
+
+    /// Changes a guild's settings, then saves them all.
+    pub(crate) async fn update(
+        &self,
+        guild: Id<GuildMarker>,
+        f: impl FnOnce(&mut GuildSettings)
+    ) -> Result<()> {
+        f(&mut self.guilds.entry(guild).or_default());
+        self.save().await
+    }
+
+    async fn save(&self) -> Result<()> {
+        let _guard = self.save_lock.lock().await;
+
+        let guilds = self.guilds
+            .iter()
+            .map(|rf| (*rf.key(), rf.value().clone()))
+            .collect::<HashMap<_, _>>();
+        let data = serde_json::to_vec_pretty(&guilds)?;
+
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || write(&path, &data)).await?
+    }
+}
+
+/// Writes to a temporary file first, so a crash can't leave half a file behind.
+fn write(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
@@ -1,27 +1,109 @@
 //! Notes:
 //! **DO NOT** give out `Ref` to an item in [`GuildData`], if not dropped, a **dead lock** may be present.
 
-use std::collections::VecDeque;
+use std::{ collections::{ HashSet, VecDeque }, sync::{ Arc, RwLock }, time::Duration };
 
 use dashmap::DashMap;
+use rand::seq::SliceRandom;
+use tokio::task::AbortHandle;
 use songbird::{ tracks::TrackHandle, Songbird };
 
 use twilight_http::Client as HttpClient;
-use twilight_model::id::{ marker::GuildMarker, Id };
+use twilight_model::id::{
+    marker::{ ChannelMarker, GuildMarker, InteractionMarker, UserMarker },
+    Id,
+};
 
-use crate::{ cache::Cache, innertube::YouTubeAudio };
+use crate::{
+    auto_leave::LeaveReason,
+    cache::Cache,
+    config::Config,
+    dsp::{ Effects, SharedEffects },
+    audio::TrackAudio,
+    innertube::YouTubeAudio,
+    library::Library,
+    lyrics::LyricsCache,
+    settings::Settings,
+    suggestions::Suggestions,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum RepeatMode {
     No,
+    /// Replay the current track.
     Single,
+    /// Put the finished track back at the end of the queue.
+    Queue,
+}
+
+/// A track in the queue, along with who asked for it.
+#[derive(Debug, Clone)]
+pub(crate) struct QueuedTrack {
+    pub(crate) audio: TrackAudio,
+    pub(crate) requester: Id<UserMarker>,
+    /// Where to start playing from, if not from the beginning.
+    pub(crate) start: Option<Duration>,
+}
+
+impl QueuedTrack {
+    pub(crate) fn new(audio: impl Into<TrackAudio>, requester: Id<UserMarker>) -> Self {
+        Self { audio: audio.into(), requester, start: None }
+    }
+
+    pub(crate) fn with_start(mut self, start: Option<Duration>) -> Self {
+        self.start = start;
+        self
+    }
+}
+
+/// How much normalization can turn a quiet track up, in dB. More than this and it would clip.
+const MAX_NORMALIZE_BOOST_DB: f32 = 6.0;
+
+/// How many tracks are kept in each guild's history.
+const HISTORY_SIZE: usize = 50;
+
+/// A track that was played in a guild.
+#[derive(Debug, Clone)]
+pub(crate) struct HistoryEntry {
+    pub(crate) audio: TrackAudio,
+    pub(crate) requester: Id<UserMarker>,
+}
+
+/// The results of a `/berd search`, waiting for the user to pick one.
+#[derive(Debug)]
+pub(crate) struct PendingSearch {
+    /// Who searched. Only they can pick.
+    pub(crate) user: Id<UserMarker>,
+    pub(crate) results: Vec<YouTubeAudio>,
+}
+
+/// The track that will play next, added to the call ahead of time but paused, so it can start
+/// right away.
+#[derive(Debug)]
+pub(crate) struct Preloaded {
+    /// The YouTube video id.
+    pub(crate) id: String,
+    /// `None` while the track is still being prepared.
+    pub(crate) handle: Option<TrackHandle>,
 }
 
 #[derive(Debug)]
 pub(crate) struct GuildData {
-    pub(crate) queue: VecDeque<YouTubeAudio>,
+    pub(crate) queue: VecDeque<QueuedTrack>,
     pub(crate) handle: TrackHandle,
+    /// The track that `handle` is playing.
+    pub(crate) current: QueuedTrack,
     pub(crate) repeat: RepeatMode,
+    /// The volume, in percent.
+    pub(crate) volume: u8,
+    /// Users who voted to skip the current track.
+    pub(crate) skip_votes: HashSet<Id<UserMarker>>,
+    /// Set when the current track is being skipped, so that [`RepeatMode::Single`]
+    /// moves on instead of replaying it.
+    pub(crate) skipping: bool,
+    /// How many tracks in a row failed to play.
+    pub(crate) failures: u32,
+    pub(crate) preloaded: Option<Preloaded>,
 }
 
 #[derive(Debug)]
@@ -30,30 +112,140 @@ pub(crate) struct State {
     pub(crate) guild_data: DashMap<Id<GuildMarker>, GuildData>,
     pub(crate) songbird: Songbird,
     pub(crate) cache: Cache,
+    pub(crate) config: Config,
+    pub(crate) settings: Settings,
+    /// Recently played tracks per guild, the latest last. This outlives [`GuildData`].
+    pub(crate) history: DashMap<Id<GuildMarker>, VecDeque<HistoryEntry>>,
+    /// Pending [`crate::auto_leave`] timers.
+    pub(crate) leave_timers: DashMap<(Id<GuildMarker>, LeaveReason), AbortHandle>,
+    /// Search pickers that haven't been used or expired yet, by the search's interaction id.
+    pub(crate) searches: DashMap<Id<InteractionMarker>, PendingSearch>,
+    pub(crate) suggestions: Suggestions,
+    pub(crate) lyrics: LyricsCache,
+    /// The local music library. Empty if there's no music directory.
+    pub(crate) library: Library,
+    /// Audio filters per guild, shared with the tracks that use them.
+    pub(crate) effects: DashMap<Id<GuildMarker>, SharedEffects>,
 }
 
 impl State {
-    pub(crate) fn new(http: HttpClient, songbird: Songbird) -> Self {
+    pub(crate) fn new(
+        http: HttpClient,
+        songbird: Songbird,
+        config: Config,
+        settings: Settings,
+        library: Library
+    ) -> Self {
         Self {
             http,
             guild_data: DashMap::new(),
             songbird,
             cache: Cache::new(),
+            config,
+            settings,
+            history: DashMap::new(),
+            leave_timers: DashMap::new(),
+            searches: DashMap::new(),
+            suggestions: Suggestions::new(),
+            lyrics: LyricsCache::new(),
+            library,
+            effects: DashMap::new(),
         }
     }
 
-    pub(crate) fn put_handle(&self, guild: Id<GuildMarker>, handle: TrackHandle) {
+    pub(crate) fn put_handle(
+        &self,
+        guild: Id<GuildMarker>,
+        handle: TrackHandle,
+        current: QueuedTrack
+    ) {
         if let Some(mut data) = self.guild_data.get_mut(&guild) {
             data.handle = handle;
+            data.current = current;
+            data.skip_votes.clear();
+            data.skipping = false;
         } else {
             self.guild_data.insert(guild, GuildData {
                 queue: VecDeque::new(),
                 handle,
+                current,
                 repeat: RepeatMode::No,
+                volume: self.settings.get(&guild).volume,
+                skip_votes: HashSet::new(),
+                skipping: false,
+                failures: 0,
+                preloaded: None,
             });
         }
     }
 
+    /// Non-bot users in the voice channel Berd is connected to, if it's connected.
+    pub(crate) async fn listeners(&self, guild: Id<GuildMarker>) -> Option<Vec<Id<UserMarker>>> {
+        let channel: Id<ChannelMarker> = {
+            let birdx = self.songbird.get(guild)?;
+            let call = birdx.lock().await;
+            Id::from(call.current_channel()?.0)
+        };
+
+        Some(self.cache.listeners(guild, channel))
+    }
+
+    /// The volume of the current session, or the guild's default if there's none.
+    pub(crate) fn volume(&self, guild: &Id<GuildMarker>) -> u8 {
+        self.guild_data
+            .get(guild)
+            .map(|rf| rf.volume)
+            .unwrap_or_else(|| self.settings.get(guild).volume)
+    }
+
+    /// The volume to play `audio` at: the guild's volume, adjusted for the track's loudness if
+    /// normalization is on.
+    pub(crate) fn track_volume(&self, guild: &Id<GuildMarker>, audio: &TrackAudio) -> f32 {
+        let volume = f32::from(self.volume(guild)) / 100.0;
+        if !self.settings.get(guild).normalize {
+            return volume;
+        }
+
+        audio
+            .loudness_db()
+            .map_or(volume, |db| volume * (10.0f32).powf(-db.max(-MAX_NORMALIZE_BOOST_DB) / 20.0))
+    }
+
+    /// [`State::track_volume`] for the current track, and its handle.
+    pub(crate) fn current_volume(&self, guild: &Id<GuildMarker>) -> Option<(TrackHandle, f32)> {
+        let (handle, audio) = self.guild_data
+            .get(guild)
+            .map(|rf| (rf.handle.clone(), rf.current.audio.clone()))?;
+
+        Some((handle, self.track_volume(guild, &audio)))
+    }
+
+    /// The guild's audio filters. Changes to them apply to the tracks that are playing.
+    pub(crate) fn effects(&self, guild: Id<GuildMarker>) -> SharedEffects {
+        self.effects
+            .entry(guild)
+            .or_insert_with(|| {
+                let eq = self.settings.get(&guild).eq;
+                Arc::new(RwLock::new(Effects { eq, ..Default::default() }))
+            })
+            .clone()
+    }
+
+    /// How long `duration` of a track takes to play with the guild's filters, which is what
+    /// track positions are measured in.
+    pub(crate) fn playback_duration(&self, guild: Id<GuildMarker>, duration: Duration) -> Duration {
+        self.effects(guild).read().map_or(duration, |effects| effects.playback_duration(duration))
+    }
+
+    /// Whether `handle` is the track currently playing in the guild.
+    pub(crate) fn is_current_handle(
+        &self,
+        guild: &Id<GuildMarker>,
+        handle: &TrackHandle
+    ) -> bool {
+        self.guild_data.get(guild).is_some_and(|rf| rf.handle.uuid() == handle.uuid())
+    }
+
     pub(crate) fn has_guild_data(&self, guild: &Id<GuildMarker>) -> bool {
         self.guild_data.contains_key(guild)
     }
@@ -62,17 +254,310 @@ impl State {
         &self,
         guild: &Id<GuildMarker>
     ) -> Option<(Id<GuildMarker>, GuildData)> {
-        self.guild_data.remove(guild)
+        let (guild, mut data) = self.guild_data.remove(guild)?;
+        if let Some(handle) = data.preloaded.take().and_then(|preloaded| preloaded.handle) {
+            drop(handle.stop());
+        }
+
+        Some((guild, data))
     }
 
     /// You must use [`State::put_handle`] first, if there's nothing playing.
-    pub(crate) fn add_track(&self, guild: &Id<GuildMarker>, track: YouTubeAudio) {
+    pub(crate) fn add_track(&self, guild: &Id<GuildMarker>, track: QueuedTrack) {
         if let Some(mut rf) = self.guild_data.get_mut(guild) {
             rf.queue.push_back(track);
         }
     }
 
-    pub(crate) fn pop_track(&self, guild: &Id<GuildMarker>) -> Option<YouTubeAudio> {
-        if let Some(mut rf) = self.guild_data.get_mut(guild) { rf.queue.pop_front() } else { None }
+    /// Like [`State::add_track`], but the track will be played next.
+    pub(crate) fn add_track_front(&self, guild: &Id<GuildMarker>, track: QueuedTrack) {
+        if let Some(mut rf) = self.guild_data.get_mut(guild) {
+            rf.queue.push_front(track);
+        }
+    }
+
+    /// Picks the track to play after the current one ends, according to the repeat mode.
+    pub(crate) fn next_track(&self, guild: &Id<GuildMarker>) -> Option<QueuedTrack> {
+        let mut rf = self.guild_data.get_mut(guild)?;
+        // repeats play from the start, even if the track was first played from partway through
+        match rf.repeat {
+            RepeatMode::Single if !rf.skipping => Some(rf.current.clone().with_start(None)),
+            RepeatMode::No | RepeatMode::Single => rf.queue.pop_front(),
+            // a track that failed to play is dropped, rather than tried again next time around
+            RepeatMode::Queue if rf.failures > 0 => rf.queue.pop_front(),
+            RepeatMode::Queue => {
+                let current = rf.current.clone().with_start(None);
+                rf.queue.push_back(current);
+                rf.queue.pop_front()
+            }
+        }
+    }
+
+    /// Records whether the current track played or failed to, so a failed one isn't repeated.
+    ///
+    /// Returns how many tracks in a row failed to play.
+    pub(crate) fn record_outcome(&self, guild: &Id<GuildMarker>, failed: bool) -> u32 {
+        let Some(mut rf) = self.guild_data.get_mut(guild) else {
+            return 0;
+        };
+        if failed {
+            rf.failures += 1;
+            rf.skipping = true;
+        } else {
+            rf.failures = 0;
+        }
+
+        rf.failures
+    }
+
+    /// Picks the track to preload, the one [`State::next_track`] would pick right now, unless
+    /// it was picked already.
+    ///
+    /// A track preloaded earlier is dropped if it's no longer next, like after the queue was
+    /// changed.
+    pub(crate) fn claim_preload(&self, guild: &Id<GuildMarker>) -> Option<QueuedTrack> {
+        let mut rf = self.guild_data.get_mut(guild)?;
+
+        let next = match rf.repeat {
+            RepeatMode::Single if !rf.skipping => Some(rf.current.clone().with_start(None)),
+            RepeatMode::No | RepeatMode::Single => rf.queue.front().cloned(),
+            RepeatMode::Queue => {
+                rf.queue
+                    .front()
+                    .cloned()
+                    .or_else(|| Some(rf.current.clone().with_start(None)))
+            }
+        };
+        // tracks that start partway through are seeked when played, so there's little to gain
+        let next = next.filter(|next| next.start.is_none());
+
+        if let Some(preloaded) = &rf.preloaded
+            && next.as_ref().is_some_and(|next| next.audio.id() == preloaded.id)
+        {
+            return None;
+        }
+        if let Some(handle) = rf.preloaded.take().and_then(|preloaded| preloaded.handle) {
+            drop(handle.stop());
+        }
+
+        let next = next?;
+        rf.preloaded = Some(Preloaded { id: next.audio.id().to_string(), handle: None });
+        Some(next)
+    }
+
+    /// Hands over the preloaded track once it's ready. Returns `false` if it's no longer
+    /// wanted, in which case it should be stopped.
+    pub(crate) fn finish_preload(
+        &self,
+        guild: &Id<GuildMarker>,
+        id: &str,
+        handle: TrackHandle
+    ) -> bool {
+        let Some(mut rf) = self.guild_data.get_mut(guild) else {
+            return false;
+        };
+        match &mut rf.preloaded {
+            Some(preloaded) if preloaded.id == id && preloaded.handle.is_none() => {
+                preloaded.handle = Some(handle);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the next track has been preloaded and is ready to play.
+    pub(crate) fn preload_ready(&self, guild: &Id<GuildMarker>) -> bool {
+        self.guild_data
+            .get(guild)
+            .is_some_and(|rf| rf.preloaded.as_ref().is_some_and(|p| p.handle.is_some()))
+    }
+
+    /// Gives up on a preload that failed, so the track gets played the usual way.
+    pub(crate) fn cancel_preload(&self, guild: &Id<GuildMarker>, id: &str) {
+        if let Some(mut rf) = self.guild_data.get_mut(guild)
+            && rf.preloaded.as_ref().is_some_and(|p| p.id == id && p.handle.is_none())
+        {
+            rf.preloaded = None;
+        }
+    }
+
+    /// Drops the preloaded track, so the next one is prepared again.
+    pub(crate) fn drop_preload(&self, guild: &Id<GuildMarker>) {
+        let preloaded = self.guild_data.get_mut(guild).and_then(|mut rf| rf.preloaded.take());
+        if let Some(handle) = preloaded.and_then(|preloaded| preloaded.handle) {
+            drop(handle.stop());
+        }
+    }
+
+    /// Takes the preloaded track, if it's `track`. Any other preloaded track is stopped.
+    pub(crate) fn take_preloaded(
+        &self,
+        guild: &Id<GuildMarker>,
+        track: &QueuedTrack
+    ) -> Option<TrackHandle> {
+        let preloaded = self.guild_data.get_mut(guild)?.preloaded.take()?;
+        match preloaded.handle {
+            Some(handle) if preloaded.id == track.audio.id() && track.start.is_none() => {
+                Some(handle)
+            }
+            Some(handle) => {
+                drop(handle.stop());
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Stops the music and drops the guild data, so the end handler has nothing to play next.
+    ///
+    /// Returns whether there was anything playing.
+    pub(crate) fn end_session(&self, guild: &Id<GuildMarker>) -> bool {
+        let Some((_, data)) = self.remove_guild_data(guild) else {
+            return false;
+        };
+        // the track might have ended already, like while autoplay is loading the next one
+        drop(data.handle.stop());
+
+        true
+    }
+
+    /// Adds a track that started playing to the guild's history.
+    pub(crate) fn record_history(&self, guild: Id<GuildMarker>, track: &QueuedTrack) {
+        let mut history = self.history.entry(guild).or_default();
+
+        // repeating a single track shouldn't fill the history with it
+        if history.back().is_some_and(|last| last.audio.id() == track.audio.id()) {
+            return;
+        }
+        if history.len() >= HISTORY_SIZE {
+            history.pop_front();
+        }
+        history.push_back(HistoryEntry {
+            audio: track.audio.clone(),
+            requester: track.requester,
+        });
+    }
+
+    /// The track played before the current one, or the last played track if nothing is playing.
+    pub(crate) fn previous(&self, guild: &Id<GuildMarker>) -> Option<HistoryEntry> {
+        let playing = self.has_guild_data(guild);
+        let history = self.history.get(guild)?;
+        let back = if playing { 2 } else { 1 };
+
+        history.len().checked_sub(back).and_then(|index| history.get(index)).cloned()
+    }
+
+    /// Plays `track` right away, with the current track queued after it.
+    ///
+    /// The current track is taken out of the history, as it'll be recorded again when it plays
+    /// next. `track` isn't recorded twice either, since it's the last one left.
+    pub(crate) fn play_previous(
+        &self,
+        guild: &Id<GuildMarker>,
+        track: QueuedTrack
+    ) -> anyhow::Result<bool> {
+        let Some(mut rf) = self.guild_data.get_mut(guild) else {
+            return Ok(false);
+        };
+
+        // the queue repeat mode puts the current track back by itself
+        if !matches!(rf.repeat, RepeatMode::Queue) {
+            let current = rf.current.clone().with_start(None);
+            rf.queue.push_front(current);
+        }
+        rf.queue.push_front(track);
+        rf.skipping = true;
+        rf.handle.stop()?;
+
+        if let Some(mut history) = self.history.get_mut(guild)
+            && history.back().is_some_and(|last| last.audio.id() == rf.current.audio.id())
+        {
+            history.pop_back();
+        }
+
+        Ok(true)
+    }
+
+    /// Removes the track at `index` from the queue.
+    pub(crate) fn remove_track(
+        &self,
+        guild: &Id<GuildMarker>,
+        index: usize
+    ) -> Option<QueuedTrack> {
+        self.guild_data.get_mut(guild)?.queue.remove(index)
+    }
+
+    /// Moves the track at `from` to `to` (or the end, if `to` is past it), returning the moved
+    /// track and where it ended up.
+    pub(crate) fn move_track(
+        &self,
+        guild: &Id<GuildMarker>,
+        from: usize,
+        to: usize
+    ) -> Option<(QueuedTrack, usize)> {
+        let mut rf = self.guild_data.get_mut(guild)?;
+        let track = rf.queue.remove(from)?;
+        let to = to.min(rf.queue.len());
+        rf.queue.insert(to, track.clone());
+
+        Some((track, to))
+    }
+
+    /// Empties the queue, returning how many tracks were removed.
+    pub(crate) fn clear_queue(&self, guild: &Id<GuildMarker>) -> Option<usize> {
+        let mut rf = self.guild_data.get_mut(guild)?;
+        let count = rf.queue.len();
+        rf.queue.clear();
+
+        Some(count)
+    }
+
+    /// Shuffles the queue, returning its length.
+    pub(crate) fn shuffle_queue(&self, guild: &Id<GuildMarker>) -> Option<usize> {
+        let mut rf = self.guild_data.get_mut(guild)?;
+        rf.queue.make_contiguous().shuffle(&mut rand::rng());
+
+        Some(rf.queue.len())
+    }
+
+    /// Drops queued tracks whose video was already queued (or is playing), returning how many
+    /// were dropped.
+    pub(crate) fn dedupe_queue(&self, guild: &Id<GuildMarker>) -> Option<usize> {
+        let mut rf = self.guild_data.get_mut(guild)?;
+        let mut seen = HashSet::from([rf.current.audio.id().to_string()]);
+        let before = rf.queue.len();
+        rf.queue.retain(|track| seen.insert(track.audio.id().to_string()));
+
+        Some(before - rf.queue.len())
+    }
+
+    /// Stops the current track so the end handler moves on to the next one.
+    pub(crate) fn skip(&self, guild: &Id<GuildMarker>) -> bool {
+        let Some(mut rf) = self.guild_data.get_mut(guild) else {
+            return false;
+        };
+        rf.skipping = true;
+        drop(rf.handle.stop());
+
+        true
+    }
+
+    /// Drops every track before `index`, then skips to the track at `index`.
+    pub(crate) fn skip_to(
+        &self,
+        guild: &Id<GuildMarker>,
+        index: usize
+    ) -> anyhow::Result<Option<QueuedTrack>> {
+        let Some(mut rf) = self.guild_data.get_mut(guild) else {
+            return Ok(None);
+        };
+        if index >= rf.queue.len() {
+            return Ok(None);
+        }
+        rf.queue.drain(..index);
+        rf.skipping = true;
+        rf.handle.stop()?;
+
+        Ok(rf.queue.front().cloned())
     }
 }
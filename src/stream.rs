@@ -0,0 +1,223 @@
+use std::{
+    io::{ Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, SeekFrom },
+    pin::Pin,
+    task::{ Context, Poll },
+};
+
+use bytes::Bytes;
+use futures::{ Stream, StreamExt };
+use reqwest::{ header::RANGE, Client, Response, StatusCode };
+use songbird::input::{ AsyncMediaSource, AudioStreamError };
+use tokio::{ io::{ AsyncRead, AsyncSeek, ReadBuf }, sync::oneshot };
+
+use crate::innertube::InnerTube;
+
+/// How many bytes are requested at once. YouTube throttles requests for larger ranges.
+const CHUNK_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How many times in a row a stream is resumed after errors before giving up.
+const MAX_RESUMES: u8 = 3;
+
+/// A seekable HTTP stream, made of byte-range requests.
+///
+/// Seeking drops the current request, and the next read requests a new range from there.
+/// When the stream URL expires partway through, a fresh one is resolved and the stream resumes
+/// where it stopped.
+pub(crate) struct YouTubeStream {
+    client: Client,
+    video_id: String,
+    url: String,
+    len: u64,
+    pos: u64,
+    state: StreamState,
+    /// Set when YouTube refused a request, which means the URL has expired.
+    expired: bool,
+    /// Resumes since data was last read.
+    resumes: u8,
+}
+
+enum StreamState {
+    /// Nothing requested yet, or the last chunk was fully read.
+    Idle,
+    Connecting(oneshot::Receiver<reqwest::Result<Response>>),
+    Reading {
+        body: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send + Sync>>,
+        chunk: Bytes,
+    },
+}
+
+impl YouTubeStream {
+    pub(crate) fn new(client: Client, video_id: String, url: String, len: u64) -> Self {
+        Self {
+            client,
+            video_id,
+            url,
+            len,
+            pos: 0,
+            state: StreamState::Idle,
+            expired: false,
+            resumes: 0,
+        }
+    }
+
+    fn connect(&self) -> oneshot::Receiver<reqwest::Result<Response>> {
+        let end = (self.pos + CHUNK_SIZE).min(self.len) - 1;
+        let request = self.client
+            .get(&self.url)
+            .header(RANGE, format!("bytes={}-{}", self.pos, end));
+
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            tx.send(request.send().await).ok();
+        });
+
+        rx
+    }
+}
+
+impl AsyncRead for YouTubeStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>
+    ) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        loop {
+            match &mut this.state {
+                StreamState::Idle => {
+                    if this.pos >= this.len {
+                        return Poll::Ready(Ok(()));
+                    }
+                    this.state = StreamState::Connecting(this.connect());
+                }
+                StreamState::Connecting(rx) => {
+                    let resp = match Pin::new(rx).poll(cx) {
+                        Poll::Pending => {
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(Err(_)) => {
+                            this.state = StreamState::Idle;
+                            return Poll::Ready(Err(IoErrorKind::BrokenPipe.into()));
+                        }
+                        Poll::Ready(Ok(resp)) => resp,
+                    };
+                    this.expired = resp
+                        .as_ref()
+                        .is_ok_and(|resp| {
+                            matches!(resp.status(), StatusCode::FORBIDDEN | StatusCode::GONE)
+                        });
+                    let resp = match resp.and_then(Response::error_for_status) {
+                        Ok(resp) => resp,
+                        Err(e) => {
+                            this.state = StreamState::Idle;
+                            return Poll::Ready(Err(IoError::other(e)));
+                        }
+                    };
+
+                    this.state = StreamState::Reading {
+                        body: Box::pin(resp.bytes_stream()),
+                        chunk: Bytes::new(),
+                    };
+                }
+                StreamState::Reading { body, chunk } => {
+                    if !chunk.is_empty() {
+                        let n = chunk.len().min(buf.remaining());
+                        buf.put_slice(&chunk.split_to(n));
+                        this.pos += n as u64;
+                        this.resumes = 0;
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    match body.poll_next_unpin(cx) {
+                        Poll::Pending => {
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(Some(Ok(bytes))) => {
+                            *chunk = bytes;
+                        }
+                        Poll::Ready(Some(Err(e))) => {
+                            this.state = StreamState::Idle;
+                            return Poll::Ready(Err(IoError::other(e)));
+                        }
+                        Poll::Ready(None) => {
+                            this.state = StreamState::Idle;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsyncSeek for YouTubeStream {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> IoResult<()> {
+        let pos = match position {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        let Some(pos) = pos else {
+            return Err(IoError::new(IoErrorKind::InvalidInput, "Seek before start of stream"));
+        };
+
+        if pos != self.pos {
+            self.pos = pos;
+            self.state = StreamState::Idle;
+        }
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMediaSource for YouTubeStream {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        Some(self.len)
+    }
+
+    async fn try_resume(
+        &mut self,
+        _offset: u64
+    ) -> Result<Box<dyn AsyncMediaSource>, AudioStreamError> {
+        if self.resumes >= MAX_RESUMES {
+            return Err(AudioStreamError::Fail("Too many failed resumes".into()));
+        }
+
+        let url = if self.expired {
+            tracing::info!("Stream URL of {} expired, resolving it again", self.video_id);
+            let resolved = InnerTube::new()
+                .stream(&self.video_id).await
+                .map_err(|e| AudioStreamError::Fail(e.into()))?;
+            // another format would put the offset somewhere else entirely
+            if resolved.size != self.len {
+                return Err(AudioStreamError::Fail("The stream changed".into()));
+            }
+            resolved.url
+        } else {
+            self.url.clone()
+        };
+
+        Ok(
+            Box::new(Self {
+                client: self.client.clone(),
+                video_id: self.video_id.clone(),
+                url,
+                len: self.len,
+                // songbird's offset counts bytes read without taking seeks into account, but
+                // `pos` follows them
+                pos: self.pos,
+                state: StreamState::Idle,
+                expired: false,
+                resumes: self.resumes + 1,
+            })
+        )
+    }
+}
@@ -0,0 +1,89 @@
+//! Query suggestions for autocomplete: what users searched for before, and cached
+//! YouTube Music suggestions.
+
+use std::{ collections::VecDeque, time::Duration };
+
+use dashmap::DashMap;
+use tokio::time::Instant;
+use twilight_model::id::{ marker::{ InteractionMarker, UserMarker }, Id };
+
+/// How many searches are remembered per user.
+const RECENT_SEARCHES: usize = 10;
+
+/// How long fetched suggestions are reused for.
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The most queries kept in the cache.
+const CACHE_SIZE: usize = 500;
+
+#[derive(Debug, Default)]
+pub(crate) struct Suggestions {
+    recent: DashMap<Id<UserMarker>, VecDeque<String>>,
+    cache: DashMap<String, (Instant, Vec<String>)>,
+    /// The latest autocomplete interaction of each user, for debouncing.
+    latest: DashMap<Id<UserMarker>, Id<InteractionMarker>>,
+}
+
+impl Suggestions {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record_search(&self, user: Id<UserMarker>, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+
+        let mut recent = self.recent.entry(user).or_default();
+        recent.retain(|q| !q.eq_ignore_ascii_case(query));
+        recent.push_front(query.to_string());
+        recent.truncate(RECENT_SEARCHES);
+    }
+
+    /// The user's recent searches that start with `prefix`, the latest first.
+    pub(crate) fn recent(&self, user: Id<UserMarker>, prefix: &str) -> Vec<String> {
+        let prefix = prefix.trim().to_lowercase();
+        self.recent
+            .get(&user)
+            .map(|recent| {
+                recent
+                    .iter()
+                    .filter(|q| q.to_lowercase().starts_with(&prefix))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn cached(&self, query: &str) -> Option<Vec<String>> {
+        self.cache
+            .get(&query.to_lowercase())
+            .filter(|rf| rf.0.elapsed() < CACHE_TTL)
+            .map(|rf| rf.1.clone())
+    }
+
+    pub(crate) fn cache(&self, query: &str, terms: Vec<String>) {
+        if self.cache.len() >= CACHE_SIZE {
+            self.cache.retain(|_, (at, _)| at.elapsed() < CACHE_TTL);
+            if self.cache.len() >= CACHE_SIZE {
+                self.cache.clear();
+            }
+        }
+        self.cache.insert(query.to_lowercase(), (Instant::now(), terms));
+    }
+
+    /// Marks `interaction` as the user's latest autocomplete request.
+    pub(crate) fn begin(&self, user: Id<UserMarker>, interaction: Id<InteractionMarker>) {
+        self.latest.insert(user, interaction);
+    }
+
+    /// Whether the user has typed more since `interaction` came in.
+    pub(crate) fn is_stale(
+        &self,
+        user: Id<UserMarker>,
+        interaction: Id<InteractionMarker>
+    ) -> bool {
+        self.latest.get(&user).is_some_and(|latest| *latest != interaction)
+    }
+}
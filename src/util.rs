@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use twilight_model::channel::message::{
+    component::{ ActionRow, Button, ButtonStyle },
+    Component,
+};
+
+/// Interaction tokens expire after 15 minutes, so responses are only edited for a bit less.
+pub(crate) const EDIT_TIMEOUT: Duration = Duration::from_secs(14 * 60);
+
+/// Formats a duration as `m:ss`, or `h:mm:ss` for long ones.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if h > 0 { format!("{h}:{m:02}:{s:02}") } else { format!("{m}:{s:02}") }
+}
+
+/// The ◀ and ▶ buttons of a paged message, with custom ids like `<prefix>:<prev|next>:<page>`.
+pub(crate) fn page_buttons(prefix: &str, page: usize, pages: usize) -> Component {
+    Component::ActionRow(ActionRow {
+        components: vec![
+            page_button(prefix, "prev", "◀", page.saturating_sub(1), page == 0),
+            page_button(prefix, "next", "▶", page + 1, page + 1 >= pages)
+        ],
+    })
+}
+
+fn page_button(prefix: &str, action: &str, label: &str, page: usize, disabled: bool) -> Component {
+    Component::Button(Button {
+        custom_id: Some(format!("{prefix}:{action}:{page}")),
+        disabled,
+        emoji: None,
+        label: Some(label.to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+        sku_id: None,
+    })
+}
+
+/// Parses a timestamp like `1:30`, `1:02:03`, `90`, `90s` or `1m30s`.
+pub(crate) fn parse_timestamp(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+
+    if s.contains(':') {
+        let mut secs = 0u64;
+        for part in s.split(':') {
+            secs = secs.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+        }
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut secs = 0u64;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => {
+                return None;
+            }
+        };
+        secs = secs.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        secs = secs.checked_add(number.parse().ok()?)?;
+    }
+
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colon_timestamps() {
+        assert_eq!(parse_timestamp("1:30"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_timestamp(" 0:05 "), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn parses_unit_timestamps() {
+        assert_eq!(parse_timestamp("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("1m30s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp("1h2m3"), Some(Duration::from_secs(3723)));
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("abc"), None);
+        assert_eq!(parse_timestamp("1:xx"), None);
+        assert_eq!(parse_timestamp("m30"), None);
+        assert_eq!(parse_timestamp("1d"), None);
+    }
+
+    #[test]
+    fn rejects_overflowing_timestamps() {
+        assert_eq!(parse_timestamp("99999999999999999999"), None);
+        assert_eq!(parse_timestamp("9999999999999999h"), None);
+        assert_eq!(parse_timestamp("18446744073709551615s1s"), None);
+        assert_eq!(parse_timestamp("9999999999999999:0:0"), None);
+    }
+}